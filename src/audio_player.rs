@@ -1,16 +1,425 @@
+use std::fs;
 use std::fs::File;
 use std::io::BufReader;
-use rodio::Sink;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use rodio::{Decoder, Device, Sink, Source};
 
-pub fn play_music() -> rodio::Sink
-{
-    let device = rodio::default_output_device().unwrap();
-    let sink = Sink::new(&device);
+use crate::backend::{self, Backend, Track, Status, PlaybackState};
+use crate::library::{self, Library};
+use crate::stations;
 
-    let file = File::open("test.mp3").unwrap();
-    let source = rodio::Decoder::new(BufReader::new(file)).unwrap();
-    sink.append(source);
-    return sink;
+// name of the pseudo playlist that contains every track in the music directory
+const ALL_TRACKS_PLAYLIST: &str = "All tracks";
+
+// images in the directory of a track that are used as cover art
+const COVER_FILES: [&str; 6] = ["cover.jpg", "cover.png", "folder.jpg", "folder.png", "front.jpg", "front.png"];
+
+// plays music from a local directory through rodio
+pub struct AudioPlayer {
+    device: Device,
+    sink: Arc<Sink>, // shared with the thread that skips to the start position
+    skipping: Arc<AtomicBool>, // set until the current track is appended to the sink
+    music_dir: PathBuf,
+    library: Library, // tags of all files in the music directory
+    queue: Vec<PathBuf>, // files in the current queue
+    current: Option<usize>, // index of the current track in the queue
+    volume: i8,
+    started_at: Option<Instant>, // set while the current track is playing
+    elapsed_before: Duration, // playback time of the current track before started_at
+}
+
+impl AudioPlayer {
+    // create new AudioPlayer playing files from the given directory
+    pub fn new(music_dir: &str) -> AudioPlayer {
+        let path = PathBuf::from(music_dir);
+        if !path.is_dir() {
+            println!("Error: music directory not found: {}", music_dir);
+            std::process::exit(1);
+        }
+
+        let device = match rodio::default_output_device() {
+            Some(d) => d,
+            None => { println!("Error: no audio output device found"); std::process::exit(1); },
+        };
+        let sink = Arc::new(Sink::new(&device));
+
+        AudioPlayer {
+            device,
+            sink,
+            skipping: Arc::new(AtomicBool::new(false)),
+            library: Library::open(&path),
+            music_dir: path,
+            queue: Vec::new(),
+            current: None,
+            volume: 100,
+            started_at: None,
+            elapsed_before: Duration::from_secs(0),
+        }
+    }
+
+    // stops playback and forgets the current track
+    fn stop_track(&mut self) {
+        self.sink.stop();
+        self.current = None;
+        self.started_at = None;
+        self.elapsed_before = Duration::from_secs(0);
+    }
+
+    // starts playing the track at the given queue index from the given position
+    // a file that can not be decoded is reported, the queue advances on the next status check
+    fn start_track(&mut self, index: usize, position: Duration) -> Result<(), String> {
+        // a stopped sink can not be reused, so every track gets a new one
+        // the old one is stopped, so a skipping thread that is still running appends to a stopped sink
+        self.sink.stop();
+        self.sink = Arc::new(Sink::new(&self.device));
+        self.sink.set_volume(self.volume as f32 / 100.0);
+
+        self.current = Some(index);
+        self.elapsed_before = position;
+        self.started_at = Some(Instant::now());
+
+        let mut source = open_source(&self.queue[index])?;
+        let skipped_samples = position.as_secs() * source.sample_rate() as u64 * source.channels() as u64;
+        if skipped_samples == 0 {
+            self.skipping = Arc::new(AtomicBool::new(false));
+            self.sink.append(source);
+            return Ok(());
+        }
+
+        // rodio has no seeking, so the samples before the position are decoded and dropped
+        // this takes a while for long tracks and must not run on the audio thread of rodio
+        let sink = Arc::clone(&self.sink);
+        let skipping = Arc::new(AtomicBool::new(true));
+        self.skipping = Arc::clone(&skipping);
+        thread::spawn(move || {
+            source.nth(skipped_samples as usize - 1);
+            sink.append(source);
+            skipping.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    // path of a m3u playlist in the music directory, the extension is added if it is missing
+    fn playlist_path(&self, playlist_name: &str) -> PathBuf {
+        let mut name = playlist_name.trim().to_string();
+        if !name.ends_with(".m3u") && !name.ends_with(".m3u8") {
+            name.push_str(".m3u");
+        }
+        self.music_dir.join(name)
+    }
+
+    // changes the entries of a m3u playlist, the positions are the ones of playlist_files
+    // comments and stream urls above an entry stay with it
+    fn edit_playlist<F>(&self, playlist_name: &str, edit: F) -> Result<(), String>
+        where F: FnOnce(&mut Vec<Vec<String>>) -> Result<(), String>
+    {
+        let path = self.music_dir.join(playlist_name);
+        if playlist_name == ALL_TRACKS_PLAYLIST || path.is_dir() {
+            return Err(format!("{} is a directory, only m3u playlists can be changed", playlist_name));
+        }
+        let content = fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+        let mut header: Vec<String> = Vec::new();
+        let mut entries: Vec<Vec<String>> = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("#EXTM3U") && entries.is_empty() {
+                header.push(line.to_string());
+                continue;
+            }
+            pending.push(line.to_string());
+            if !trimmed.is_empty() && !trimmed.starts_with('#') && !stations::is_stream_url(trimmed) {
+                entries.push(std::mem::take(&mut pending));
+            }
+        }
+
+        edit(&mut entries)?;
+
+        let lines: Vec<String> = header.into_iter().chain(entries.into_iter().flatten()).chain(pending).collect();
+        fs::write(&path, lines.join("\n") + "\n").map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    // returns all files belonging to a playlist
+    fn playlist_files(&self, playlist_name: &str) -> Vec<PathBuf> {
+        let path = if playlist_name == ALL_TRACKS_PLAYLIST {
+            self.music_dir.clone()
+        }
+        else {
+            self.music_dir.join(playlist_name)
+        };
+
+        if path.is_dir() {
+            return self.library.get_tracks_in(&path).into_iter().map(|t| PathBuf::from(t.file)).collect();
+        }
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        if let Ok(content) = fs::read_to_string(&path) {
+            // every line of a m3u file that is not a comment is a path
+            // relative paths are relative to the music directory
+            // stream urls are skipped as they can not be played locally
+            for line in content.lines().map(|l| l.trim()) {
+                if !line.is_empty() && !line.starts_with('#') && !stations::is_stream_url(line) {
+                    files.push(self.music_dir.join(line));
+                }
+            }
+        }
+
+        files
+    }
 }
 
+impl Backend for AudioPlayer {
+    // starts playback if not already playing
+    fn play(&mut self) -> Result<(), String> {
+        if self.current.is_none() {
+            if !self.queue.is_empty() {
+                self.start_track(0, Duration::from_secs(0))?;
+            }
+        }
+        else if self.started_at.is_none() {
+            self.sink.play();
+            self.started_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    // pauses playback if not already paused
+    fn pause(&mut self) -> Result<(), String> {
+        if let Some(started_at) = self.started_at.take() {
+            self.sink.pause();
+            self.elapsed_before += started_at.elapsed();
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        self.stop_track();
+        Ok(())
+    }
+
+    // returns true if a track is currently playing
+    // this also advances the queue if the current track has ended
+    fn is_playing(&mut self) -> bool {
+        if self.started_at.is_some() && self.sink.empty() && !self.skipping.load(Ordering::SeqCst) {
+            match self.current {
+                // nobody waits for the result, a file that can not be decoded is skipped by the next check
+                Some(i) if i + 1 < self.queue.len() => { let _ = self.start_track(i + 1, Duration::from_secs(0)); },
+                _ => self.stop_track(),
+            }
+        }
+
+        self.started_at.is_some()
+    }
+
+    // clears current queue
+    fn clear_queue(&mut self) -> Result<(), String> {
+        self.stop_track();
+        self.queue.clear();
+        Ok(())
+    }
+
+    // streams, opus and m4a files can not be decoded by rodio, they are only kept in playlists
+    fn add_to_queue(&mut self, track: &Track) -> Result<(), String> {
+        if track.is_stream() {
+            return Err(format!("{} is a stream, only files can be played without mpd", track.file));
+        }
+        if !library::is_playable_file(Path::new(&track.file)) {
+            return Err(format!("{} can not be played without mpd", track.file));
+        }
+        self.queue.push(PathBuf::from(&track.file));
+        Ok(())
+    }
+
+    // appends the file to a m3u playlist, the playlist is created if it does not exist
+    fn add_to_playlist(&mut self, playlist_name: &str, track: &Track) -> Result<(), String> {
+        let path = self.playlist_path(playlist_name);
+        let mut content = fs::read_to_string(&path).unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&track.file);
+        content.push('\n');
+
+        fs::write(&path, content).map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    // writes the queue to a m3u playlist, an existing file is replaced
+    fn save_queue(&mut self, playlist_name: &str) -> Result<(), String> {
+        let path = self.playlist_path(playlist_name);
+        let content: String = self.get_queue()?.iter().map(|track| format!("{}\n", track.file)).collect();
+
+        fs::write(&path, content).map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    fn remove_from_playlist(&mut self, playlist_name: &str, positions: &[u32]) -> Result<(), String> {
+        self.edit_playlist(playlist_name.trim(), |entries| backend::remove_positions(entries, positions))
+    }
+
+    fn move_in_playlist(&mut self, playlist_name: &str, from: u32, to: u32) -> Result<(), String> {
+        self.edit_playlist(playlist_name.trim(), |entries| backend::move_position(entries, from, to))
+    }
+
+    fn play_position(&mut self, position: u32) -> Result<(), String> {
+        if (position as usize) < self.queue.len() {
+            self.start_track(position as usize, Duration::from_secs(0))?;
+        }
+        Ok(())
+    }
+
+    fn get_queue(&mut self) -> Result<Vec<Track>, String> {
+        Ok(self.queue.iter().map(|path| self.library.get_track(path)).collect())
+    }
+
+    // get all names of all playlists
+    // playlists are the m3u files and top level directories of the music directory
+    fn get_all_playlist_names(&mut self) -> Result<Vec<String>, String> {
+        self.library.refresh();
+
+        let mut names: Vec<String> = vec![String::from(ALL_TRACKS_PLAYLIST)];
+
+        let mut entries: Vec<PathBuf> = match fs::read_dir(&self.music_dir) {
+            Ok(dir) => dir.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => Vec::new(),
+        };
+        entries.sort();
+
+        for entry in entries {
+            let is_playlist = match entry.extension().and_then(|e| e.to_str()) {
+                Some(ext) => ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"),
+                None => false,
+            };
+
+            if entry.is_dir() || is_playlist {
+                if let Some(name) = entry.file_name().and_then(|n| n.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    // get all tracks in a playlist
+    fn get_all_tracks_in_playlist(&mut self, playlist_name: &str) -> Result<Vec<Track>, String> {
+        self.library.refresh();
+
+        Ok(self.playlist_files(playlist_name.trim()).iter()
+            .map(|path| self.library.get_track(path))
+            .collect())
+    }
+
+    fn get_library(&mut self) -> Result<Vec<Track>, String> {
+        self.library.refresh();
+        Ok(self.library.get_tracks_in(&self.music_dir))
+    }
+
+    // set playback volume (0 - 100)
+    fn set_volume(&mut self, volume: i8) -> Result<(), String> {
+        self.volume = volume.clamp(0, 100);
+        self.sink.set_volume(self.volume as f32 / 100.0);
+        Ok(())
+    }
+
+    // switch current song to next song in queue
+    fn next_song(&mut self) -> Result<(), String> {
+        if let Some(i) = self.current {
+            if i + 1 < self.queue.len() {
+                self.start_track(i + 1, Duration::from_secs(0))?;
+            }
+        }
+        Ok(())
+    }
+
+    // switch current song to prev song in queue
+    fn prev_song(&mut self) -> Result<(), String> {
+        if let Some(i) = self.current {
+            self.start_track(i.saturating_sub(1), Duration::from_secs(0))?;
+        }
+        Ok(())
+    }
+
+    // seek to 'seconds' seconds in current song
+    fn seek(&mut self, seconds: i64) -> Result<(), String> {
+        if let Some(i) = self.current {
+            let was_playing = self.started_at.is_some();
+            self.start_track(i, Duration::from_secs(seconds.max(0) as u64))?;
+            if !was_playing {
+                self.pause()?;
+            }
+        }
+        Ok(())
+    }
+
+    // loads a playlist into the queue
+    fn load_playlist(&mut self, playlist_name: &str, start: u32, end: u32) -> Result<(), String> {
+        let files = self.playlist_files(playlist_name.trim());
+        let end = (end as usize).min(files.len());
+        let start = (start as usize).min(end);
+        self.queue.extend_from_slice(&files[start..end]);
+        Ok(())
+    }
+
+    // get elapsed time of currently playing song in seconds
+    fn get_elapsed(&mut self) -> i64 {
+        let mut elapsed = self.elapsed_before;
+        if let Some(started_at) = self.started_at {
+            elapsed += started_at.elapsed();
+        }
+
+        elapsed.as_secs() as i64
+    }
+
+    fn get_status(&mut self) -> Result<Status, String> {
+        let state = if self.is_playing() {
+            PlaybackState::Play
+        }
+        else if self.current.is_some() {
+            PlaybackState::Pause
+        }
+        else {
+            PlaybackState::Stop
+        };
+
+        Ok(Status {
+            state,
+            volume: self.volume,
+            elapsed: self.get_elapsed(),
+            queue_position: self.current.map(|i| i as u32),
+            queue_length: self.queue.len() as u32,
+            repeat: false,
+            random: false,
+            single: false,
+            consume: false,
+        })
+    }
+
+    fn get_current_track(&mut self) -> Result<Option<Track>, String> {
+        Ok(self.current.map(|i| self.library.get_track(&self.queue[i])))
+    }
+
+    // looks for a cover image next to the file
+    fn get_cover_art(&mut self, track: &Track) -> Option<Vec<u8>> {
+        let dir = Path::new(&track.file).parent()?;
+        COVER_FILES.iter().find_map(|name| fs::read(dir.join(name)).ok())
+    }
+
+    fn library_changed(&mut self) -> bool {
+        self.library.changed()
+    }
+
+    // stop the audio output
+    fn close_conn(&mut self) {
+        self.stop_track();
+    }
+}
+
+fn open_source(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| format!("could not decode {}: {}", path.display(), e))
+}
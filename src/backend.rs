@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use crate::stations;
+
+// backend neutral representation of a track
+#[allow(dead_code)]
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Track {
+    pub file: String, // path or uri of the track, used to identify it
+    pub name: Option<String>, // name of the station if the track is a stream
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<u64>, // duration in seconds
+    pub tags: BTreeMap<String, String>, // all other tags reported by the backend
+}
+
+impl Track {
+    // get the title or a placeholder if the track has no title
+    // streams without a title are shown with their station name or url
+    pub fn get_title(&self) -> String {
+        match (&self.title, &self.name) {
+            (Some(title), _) => title.to_owned(),
+            (None, Some(name)) => name.to_owned(),
+            (None, None) if self.is_stream() => self.file.to_owned(),
+            (None, None) => String::from("unknown title"),
+        }
+    }
+
+    // get the artist, falls back to the album artist
+    pub fn get_artist(&self) -> String {
+        match (&self.artist, &self.album_artist) {
+            (Some(artist), _) => artist.to_owned(),
+            (None, Some(album_artist)) => album_artist.to_owned(),
+            (None, None) => String::from("unknown artist"),
+        }
+    }
+
+    pub fn get_album(&self) -> String {
+        match &self.album {
+            Some(album) => album.to_owned(),
+            None => String::from("unknown album"),
+        }
+    }
+
+    // get a tag by its name, ignoring the case (e.g. "artist", "Genre", "albumartist")
+    pub fn get_tag(&self, name: &str) -> Option<String> {
+        match name.to_lowercase().as_str() {
+            "title" => self.title.clone(),
+            "artist" => self.artist.clone(),
+            "albumartist" => self.album_artist.clone(),
+            "album" => self.album.clone(),
+            "file" => Some(self.file.clone()),
+            "name" => self.name.clone(),
+            lower => self.tags.iter().find(|(tag, _)| tag.to_lowercase() == lower).map(|(_, value)| value.clone()),
+        }
+    }
+
+    // get the duration of the track in seconds, 0 if unknown
+    pub fn get_duration(&self) -> i64 {
+        self.duration.unwrap_or(0) as i64
+    }
+
+    // returns true if the track is an internet stream (e.g. a radio station)
+    pub fn is_stream(&self) -> bool {
+        stations::is_stream_url(&self.file)
+    }
+}
+
+// removes the elements at the positions, used by backends that edit their playlists themselves
+pub fn remove_positions<T>(items: &mut Vec<T>, positions: &[u32]) -> Result<(), String> {
+    let mut positions = positions.to_vec();
+    positions.sort_unstable();
+    positions.dedup();
+
+    // removing from the end keeps the other positions valid
+    for position in positions.into_iter().rev() {
+        if position as usize >= items.len() {
+            return Err(format!("no track at position {}", position));
+        }
+        items.remove(position as usize);
+    }
+
+    Ok(())
+}
+
+// moves the element at from to the position to
+pub fn move_position<T>(items: &mut Vec<T>, from: u32, to: u32) -> Result<(), String> {
+    if from as usize >= items.len() || to as usize >= items.len() {
+        return Err(format!("can not move the track at position {} to {}", from, to));
+    }
+
+    let item = items.remove(from as usize);
+    items.insert(to as usize, item);
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackState {
+    Play,
+    Pause,
+    Stop,
+}
+
+// snapshot of the state of a backend
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Status {
+    pub state: PlaybackState,
+    pub volume: i8, // 0 - 100, -1 if the backend has no volume control
+    pub elapsed: i64, // elapsed time of the current track in seconds
+    pub queue_position: Option<u32>, // position of the current track in the queue
+    pub queue_length: u32,
+    pub repeat: bool,
+    pub random: bool,
+    pub single: bool,
+    pub consume: bool,
+}
+
+// interface that all music backends (mpd, local playback, ...) implement
+// PlayerInterface only talks to the backend through this trait
+// the methods that talk to the server return its error message, so it can be shown to the user
+pub trait Backend {
+    // +------------------+
+    // | playback control |
+    // +------------------+
+
+    fn play(&mut self) -> Result<(), String>;
+    fn pause(&mut self) -> Result<(), String>;
+
+    // Play if paused or stopped, pause if playing
+    fn toggle_play_pause(&mut self) -> Result<(), String> {
+        if self.get_status()?.state == PlaybackState::Play {
+            self.pause()
+        }
+        else {
+            self.play()
+        }
+    }
+
+    fn stop(&mut self) -> Result<(), String>;
+    fn next_song(&mut self) -> Result<(), String>;
+    fn prev_song(&mut self) -> Result<(), String>;
+
+    // seek to 'seconds' seconds in current song
+    fn seek(&mut self, seconds: i64) -> Result<(), String>;
+
+    // set playback volume (0 - 100)
+    fn set_volume(&mut self, volume: i8) -> Result<(), String>;
+
+    // playback options, backends that do not support an option ignore it
+    fn set_repeat(&mut self, _enabled: bool) -> Result<(), String> {
+        Ok(())
+    }
+    fn set_random(&mut self, _enabled: bool) -> Result<(), String> {
+        Ok(())
+    }
+    fn set_single(&mut self, _enabled: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    // +------------------+
+    // | queue operations |
+    // +------------------+
+
+    fn clear_queue(&mut self) -> Result<(), String>;
+    fn add_to_queue(&mut self, track: &Track) -> Result<(), String>;
+    fn get_queue(&mut self) -> Result<Vec<Track>, String>;
+
+    // appends the tracks to the queue, backends with a server send them at once
+    fn add_all_to_queue(&mut self, tracks: &[Track]) -> Result<(), String> {
+        tracks.iter().try_for_each(|track| self.add_to_queue(track))
+    }
+
+    // starts playing the track at a position of the queue
+    fn play_position(&mut self, position: u32) -> Result<(), String>;
+
+    // appends a track (or stream url) to a stored playlist
+    fn add_to_playlist(&mut self, playlist_name: &str, track: &Track) -> Result<(), String>;
+
+    // stores the queue as a playlist, a playlist with the same name is replaced
+    fn save_queue(&mut self, playlist_name: &str) -> Result<(), String>;
+
+    // removes the tracks at the positions from a stored playlist
+    fn remove_from_playlist(&mut self, playlist_name: &str, positions: &[u32]) -> Result<(), String>;
+
+    // moves the track at a position of a stored playlist to another position
+    fn move_in_playlist(&mut self, playlist_name: &str, from: u32, to: u32) -> Result<(), String>;
+
+    // loads the tracks start..end of a playlist into the queue
+    fn load_playlist(&mut self, playlist_name: &str, start: u32, end: u32) -> Result<(), String>;
+
+    // +-----------+
+    // | playlists |
+    // +-----------+
+
+    fn get_all_playlist_names(&mut self) -> Result<Vec<String>, String>;
+    fn get_all_tracks_in_playlist(&mut self, playlist_name: &str) -> Result<Vec<Track>, String>;
+
+    // all tracks in the music database, backends without a database return none
+    fn get_library(&mut self) -> Result<Vec<Track>, String> {
+        Ok(Vec::new())
+    }
+
+    // returns true if the library changed since the last call, e.g. a scan finished in the background
+    fn library_changed(&mut self) -> bool {
+        false
+    }
+
+    // +---------------------+
+    // | status and metadata |
+    // +---------------------+
+
+    fn get_status(&mut self) -> Result<Status, String>;
+    fn get_current_track(&mut self) -> Result<Option<Track>, String>;
+
+    // the cover image of a track as it is stored (jpeg, png, ...), None if there is none
+    fn get_cover_art(&mut self, _track: &Track) -> Option<Vec<u8>> {
+        None
+    }
+
+    // returns true if a track is currently playing
+    fn is_playing(&mut self) -> bool {
+        self.get_status().map(|status| status.state == PlaybackState::Play).unwrap_or(false)
+    }
+
+    // get elapsed time of currently playing song in seconds, 0 if nothing is playing
+    fn get_elapsed(&mut self) -> i64 {
+        self.get_status().map(|status| status.elapsed).unwrap_or(0)
+    }
+
+    // get the queue position of the current song, None if there is none
+    #[allow(dead_code)]
+    fn get_current_song_id(&mut self) -> Option<u32> {
+        self.get_status().ok().and_then(|status| status.queue_position)
+    }
+
+    // returns true if the backend streams its output over http (mpd httpd output)
+    // an error tells why the output could not be looked up
+    fn has_stream_output(&mut self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    // returns false if the connection to the server was lost
+    fn is_connected(&mut self) -> bool {
+        true
+    }
+
+    // cleanup (close connections, stop audio output etc.)
+    fn close_conn(&mut self);
+}
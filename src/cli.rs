@@ -0,0 +1,514 @@
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use argh::FromArgs;
+use mpd::idle::Subsystem;
+use serde_json::{json, Value};
+
+use crate::backend::{Backend, Track, Status, PlaybackState};
+use crate::player::Player;
+use crate::player_interface::PlayerInterface;
+use crate::template;
+
+// exit codes of the subcommands
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_USAGE: i32 = 1; // invalid arguments, also used by argh
+pub const EXIT_CONNECTION: i32 = 2; // the server could not be reached
+pub const EXIT_FAILED: i32 = 3; // the command could not be executed, e.g. nothing is playing
+
+// output of the status command if no format is given
+const DEFAULT_STATUS_FORMAT: &str = "%state%[: [%artist% - ]%title%][ \\[%elapsed%[/%duration%]\\]]";
+
+// how often the state is checked by status --follow
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+// commands that control the server without opening the TUI
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    Play(PlayCommand),
+    Pause(PauseCommand),
+    Toggle(ToggleCommand),
+    Next(NextCommand),
+    Prev(PrevCommand),
+    Stop(StopCommand),
+    Seek(SeekCommand),
+    Volume(VolumeCommand),
+    Add(AddCommand),
+    Load(LoadCommand),
+    Current(CurrentCommand),
+    Queue(QueueCommand),
+    Playlists(PlaylistsCommand),
+    Status(StatusCommand),
+    Events(EventsCommand),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "play", description = "start playback")]
+pub struct PlayCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "pause", description = "pause playback")]
+pub struct PauseCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "toggle", description = "pause if playing, play otherwise")]
+pub struct ToggleCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "next", description = "play the next song in the queue")]
+pub struct NextCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "prev", description = "play the previous song in the queue")]
+pub struct PrevCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "stop", description = "stop playback")]
+pub struct StopCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "seek", description = "seek in the current song")]
+pub struct SeekCommand {
+    #[argh(positional)]
+    #[argh(description = "position as seconds or m:ss, relative with a leading + or -")]
+    pub position: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "volume", description = "show or set the volume")]
+pub struct VolumeCommand {
+    #[argh(positional)]
+    #[argh(description = "volume from 0 to 100, relative with a leading + or -")]
+    pub volume: Option<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add", description = "append files or stream urls to the queue")]
+pub struct AddCommand {
+    #[argh(positional)]
+    #[argh(description = "uris of the songs")]
+    pub uris: Vec<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "load", description = "append a stored playlist to the queue")]
+pub struct LoadCommand {
+    #[argh(positional)]
+    #[argh(description = "name of the playlist")]
+    pub playlist: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "current", description = "print the current song")]
+pub struct CurrentCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "queue", description = "print the queue")]
+pub struct QueueCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "playlists", description = "print the names of the stored playlists")]
+pub struct PlaylistsCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "status", description = "print the state of the player, e.g. for status bars")]
+pub struct StatusCommand {
+    #[argh(switch)]
+    #[argh(description = "print all information as json")]
+    pub json: bool,
+
+    #[argh(option)]
+    #[argh(description = "output format with placeholders like %artist%, %title%, %elapsed%, %duration%, sections in [] are only shown if all their placeholders have a value")]
+    pub format: Option<String>,
+
+    #[argh(switch)]
+    #[argh(description = "keep running and print a new line whenever the output changes")]
+    pub follow: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "events", description = "print one json object per line for every change on the server")]
+pub struct EventsCommand {}
+
+// connects to the server, runs the command and returns the exit code
+pub fn run(command: &Command, ip_with_port: &String) -> i32 {
+    let mut player = match Player::connect(ip_with_port) {
+        Ok(p) => p,
+        Err(_) => {
+            eprintln!("Error connecting to the server with address: {}", ip_with_port);
+            return EXIT_CONNECTION;
+        },
+    };
+
+    let exit_code = execute(command, &mut player);
+    player.close_conn();
+
+    exit_code
+}
+
+fn execute(command: &Command, player: &mut Player) -> i32 {
+    // errors of the server fail the command
+    match execute_command(command, player) {
+        Ok(exit_code) => exit_code,
+        Err(message) => fail(&message),
+    }
+}
+
+fn execute_command(command: &Command, player: &mut Player) -> Result<i32, String> {
+    match command {
+        Command::Play(_) => player.play()?,
+        Command::Pause(_) => player.pause()?,
+        Command::Toggle(_) => player.toggle_play_pause()?,
+        Command::Next(_) => player.next_song()?,
+        Command::Prev(_) => player.prev_song()?,
+        Command::Stop(_) => player.stop()?,
+
+        Command::Seek(seek) => {
+            let duration = match player.get_current_track()? {
+                Some(track) => track.get_duration(),
+                None => return Ok(fail("nothing is playing")),
+            };
+            let position = match parse_relative(&seek.position, parse_time, player.get_elapsed()) {
+                Some(p) => p,
+                None => return Ok(usage(&format!("invalid position: {}", seek.position))),
+            };
+
+            if duration > 0 && position > duration {
+                return Ok(fail("position is after the end of the song"));
+            }
+            player.seek(position.max(0))?;
+        },
+
+        Command::Volume(volume) => {
+            let current = player.get_status()?.volume;
+            if current < 0 {
+                return Ok(fail("the server has no volume control"));
+            }
+
+            match &volume.volume {
+                Some(value) => match parse_relative(value, |v| v.parse::<i64>().ok(), current as i64) {
+                    Some(v) => player.set_volume(v.clamp(0, 100) as i8)?,
+                    None => return Ok(usage(&format!("invalid volume: {}", value))),
+                },
+                None => println!("{}%", current),
+            }
+        },
+
+        Command::Add(add) => {
+            if add.uris.is_empty() {
+                return Ok(usage("no uri given"));
+            }
+            for uri in add.uris.iter() {
+                player.add_to_queue(&Track { file: uri.clone(), ..Track::default() })?;
+            }
+        },
+
+        Command::Load(load) => {
+            if !player.get_all_playlist_names()?.contains(&load.playlist) {
+                return Ok(fail(&format!("no playlist named {}", load.playlist)));
+            }
+            let length = player.get_all_tracks_in_playlist(&load.playlist)?.len() as u32;
+            player.load_playlist(&load.playlist, 0, length)?;
+        },
+
+        Command::Current(_) => match player.get_current_track()? {
+            Some(track) => println!("{}", format_track(&track)),
+            None => return Ok(fail("nothing is playing")),
+        },
+
+        Command::Queue(_) => {
+            let status = player.get_status()?;
+            for (i, track) in player.get_queue()?.iter().enumerate() {
+                // the current song is marked like in mpc
+                let marker = if status.queue_position == Some(i as u32) && status.state != PlaybackState::Stop { ">" } else { " " };
+                println!("{}{:>3}. {}", marker, i + 1, format_track(track));
+            }
+        },
+
+        Command::Playlists(_) => {
+            for name in player.get_all_playlist_names()? {
+                println!("{}", name);
+            }
+        },
+
+        Command::Status(status) => return print_status(status, player),
+        Command::Events(_) => return print_events(player),
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+// prints the status once or, with --follow, every time it changes
+fn print_status(command: &StatusCommand, player: &mut Player) -> Result<i32, String> {
+    let mut last_line: Option<String> = None;
+
+    loop {
+        let status = player.get_status()?;
+        let track = player.get_current_track()?;
+
+        let line = if command.json {
+            status_json(&status, &track).to_string()
+        }
+        else {
+            let format = command.format.clone().unwrap_or(String::from(DEFAULT_STATUS_FORMAT));
+            template::render(&format, &|name: &str| status_value(name, &status, &track))
+        };
+
+        if !command.follow {
+            println!("{}", line);
+            return Ok(EXIT_SUCCESS);
+        }
+
+        // status bars read line by line, so the output has to be flushed
+        if last_line.as_ref() != Some(&line) {
+            println!("{}", line);
+            let _ = std::io::stdout().flush();
+            last_line = Some(line);
+        }
+
+        thread::sleep(FOLLOW_INTERVAL);
+
+        if !player.is_connected() {
+            eprintln!("Error: lost the connection to the server");
+            return Ok(EXIT_CONNECTION);
+        }
+    }
+}
+
+// waits for changes on the server and prints them as ndjson until the connection is lost
+fn print_events(player: &mut Player) -> Result<i32, String> {
+    let mut last_status = player.get_status()?;
+    let mut last_track = player.get_current_track()?;
+
+    loop {
+        let subsystems = match player.wait_for_changes() {
+            Ok(s) => s,
+            Err(_) => {
+                eprintln!("Error: lost the connection to the server");
+                return Ok(EXIT_CONNECTION);
+            },
+        };
+
+        let status = player.get_status()?;
+        let track = player.get_current_track()?;
+        let mut events: Vec<Value> = Vec::new();
+
+        for subsystem in subsystems {
+            match subsystem {
+                // the player subsystem covers song changes, play/pause/stop and seeking
+                Subsystem::Player => {
+                    if track != last_track {
+                        events.push(json!({ "event": "song", "song": track.as_ref().map(track_json) }));
+                    }
+                    if status.state != last_status.state {
+                        events.push(json!({ "event": "state", "state": state_name(status.state) }));
+                    }
+                    if track == last_track && status.state == last_status.state {
+                        events.push(json!({ "event": "seek", "elapsed": status.elapsed }));
+                    }
+                },
+                Subsystem::Queue => events.push(json!({
+                    "event": "queue",
+                    "queue_length": status.queue_length,
+                    "queue_position": status.queue_position,
+                })),
+                Subsystem::Playlist => events.push(json!({
+                    "event": "stored_playlists",
+                    "playlists": player.get_all_playlist_names()?,
+                })),
+                Subsystem::Mixer => events.push(json!({
+                    "event": "volume",
+                    "volume": if status.volume >= 0 { Some(status.volume) } else { None },
+                })),
+                Subsystem::Options => events.push(json!({
+                    "event": "options",
+                    "repeat": status.repeat,
+                    "random": status.random,
+                    "single": status.single,
+                    "consume": status.consume,
+                })),
+                Subsystem::Database => events.push(json!({ "event": "database" })),
+                Subsystem::Update => events.push(json!({ "event": "database_update" })),
+                other => events.push(json!({ "event": other.to_string() })),
+            }
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        for mut event in events {
+            event["time"] = json!(time);
+            println!("{}", event);
+        }
+        let _ = std::io::stdout().flush();
+
+        last_status = status;
+        last_track = track;
+    }
+}
+
+pub fn state_name(state: PlaybackState) -> &'static str {
+    match state {
+        PlaybackState::Play => "playing",
+        PlaybackState::Pause => "paused",
+        PlaybackState::Stop => "stopped",
+    }
+}
+
+// value of a placeholder in the status format
+fn status_value(name: &str, status: &Status, track: &Option<Track>) -> Option<String> {
+    let on_off = |enabled: bool| Some(String::from(if enabled { "on" } else { "off" }));
+
+    match name {
+        "state" => return Some(state_name(status.state).to_string()),
+        "volume" => return if status.volume >= 0 { Some(status.volume.to_string()) } else { None },
+        "position" => return status.queue_position.map(|p| (p + 1).to_string()),
+        "length" => return Some(status.queue_length.to_string()),
+        "repeat" => return on_off(status.repeat),
+        "random" => return on_off(status.random),
+        "single" => return on_off(status.single),
+        "consume" => return on_off(status.consume),
+        _ => {},
+    }
+
+    // the remaining placeholders describe the current song
+    let track = track.as_ref()?;
+    match name {
+        "title" => Some(track.get_title()),
+        "artist" => track.artist.clone(),
+        "albumartist" => track.album_artist.clone(),
+        "album" => track.album.clone(),
+        "name" => track.name.clone(),
+        "file" => Some(track.file.clone()),
+        "elapsed" => Some(PlayerInterface::transform_to_time_string(status.elapsed)),
+        "duration" => track.duration.map(|d| PlayerInterface::transform_to_time_string(d as i64)),
+        // any other tag of the song, e.g. %genre% or %date%
+        _ => track.tags.iter().find(|(tag, _)| tag.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone()),
+    }
+}
+
+pub fn track_json(track: &Track) -> Value {
+    json!({
+        "file": track.file,
+        "title": track.title,
+        "artist": track.artist,
+        "album_artist": track.album_artist,
+        "album": track.album,
+        "name": track.name,
+        "duration": track.duration,
+        "tags": track.tags,
+    })
+}
+
+pub fn status_json(status: &Status, track: &Option<Track>) -> Value {
+    json!({
+        "state": state_name(status.state),
+        "song": track.as_ref().map(track_json),
+        "elapsed": status.elapsed,
+        "duration": track.as_ref().and_then(|t| t.duration),
+        "volume": if status.volume >= 0 { Some(status.volume) } else { None },
+        "queue_position": status.queue_position,
+        "queue_length": status.queue_length,
+        "repeat": status.repeat,
+        "random": status.random,
+        "single": status.single,
+        "consume": status.consume,
+    })
+}
+
+fn fail(message: &str) -> i32 {
+    eprintln!("Error: {}", message);
+    EXIT_FAILED
+}
+
+fn usage(message: &str) -> i32 {
+    eprintln!("Error: {}", message);
+    EXIT_USAGE
+}
+
+// "artist - title", streams show the station instead of the artist
+pub fn format_track(track: &Track) -> String {
+    if track.is_stream() {
+        format!("{} - {}", track.name.clone().unwrap_or(track.file.clone()), track.title.clone().unwrap_or(String::from("live stream")))
+    }
+    else {
+        format!("{} - {}", track.get_artist(), track.get_title())
+    }
+}
+
+// parses "90", "1:30" or "1:02:30" into seconds
+pub fn parse_time(value: &str) -> Option<i64> {
+    let mut seconds: i64 = 0;
+    for part in value.split(':') {
+        let number = part.trim().parse::<i64>().ok()?;
+        if number < 0 {
+            return None;
+        }
+        seconds = seconds * 60 + number;
+    }
+
+    Some(seconds)
+}
+
+// parses an absolute value or a value relative to 'current' with a leading + or -
+pub fn parse_relative<F: Fn(&str) -> Option<i64>>(value: &str, parse: F, current: i64) -> Option<i64> {
+    let value = value.trim();
+
+    if let Some(offset) = value.strip_prefix('+') {
+        Some(current + parse(offset)?)
+    }
+    else if let Some(offset) = value.strip_prefix('-') {
+        Some(current - parse(offset)?)
+    }
+    else {
+        parse(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_mpd::{self, FakeMpd};
+    use crate::memory_player::demo_playlists;
+
+    // a player connected to a new fake server, the first demo playlist is in the queue
+    fn connect() -> (FakeMpd, Player) {
+        let (server, mut player) = fake_mpd::connect_fake();
+
+        let playlist = demo_playlists().remove(0).0;
+        assert_eq!(execute(&Command::Load(LoadCommand { playlist }), &mut player), EXIT_SUCCESS);
+        (server, player)
+    }
+
+    #[test]
+    fn toggle_starts_playback_when_stopped() {
+        let (server, mut player) = connect();
+
+        assert_eq!(execute(&Command::Toggle(ToggleCommand {}), &mut player), EXIT_SUCCESS);
+        assert!(server.state().state == PlaybackState::Play);
+
+        assert_eq!(execute(&Command::Toggle(ToggleCommand {}), &mut player), EXIT_SUCCESS);
+        assert!(server.state().state == PlaybackState::Pause);
+
+        // play and pause do not toggle
+        assert_eq!(execute(&Command::Pause(PauseCommand {}), &mut player), EXIT_SUCCESS);
+        assert!(server.state().state == PlaybackState::Pause);
+        assert_eq!(execute(&Command::Play(PlayCommand {}), &mut player), EXIT_SUCCESS);
+        assert_eq!(execute(&Command::Play(PlayCommand {}), &mut player), EXIT_SUCCESS);
+        assert!(server.state().state == PlaybackState::Play);
+    }
+
+    #[test]
+    fn server_errors_fail_the_command() {
+        let (server, mut player) = connect();
+
+        // the server does not seek while stopped
+        let seek = Command::Seek(SeekCommand { position: String::from("10") });
+        assert_eq!(execute(&seek, &mut player), EXIT_FAILED);
+
+        let length = server.state().queue.len();
+        let add = Command::Add(AddCommand { uris: vec![String::from("missing/file.mp3")] });
+        assert_eq!(execute(&add, &mut player), EXIT_FAILED);
+        assert_eq!(server.state().queue.len(), length);
+    }
+}
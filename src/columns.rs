@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use crate::backend::Track;
+use crate::config::ColumnConfig;
+use crate::template;
+use crate::util::table::Cell;
+
+// tags that can be shown in a column, templates can use them as %name% as well
+pub const COLUMN_TAGS: [&str; 14] = [
+    "title", "artist", "album", "albumartist", "track", "disc", "date", "genre", "composer",
+    "filename", "file", "length", "bitrate", "rating",
+];
+
+// the cell of a track in a column, the template is used if the column has one
+pub fn cell(track: &Track, column: &ColumnConfig) -> Cell {
+    let tag = match (&column.format, &column.tag) {
+        (Some(format), _) => return Cell::Text(template::render(format, &|name: &str| tag_value(track, name))),
+        (None, Some(tag)) => tag.to_lowercase(),
+        (None, None) => return Cell::Text(String::new()),
+    };
+
+    // numbers and lengths are right aligned, values that are no numbers are shown as they are
+    let value = || tag_value(track, &tag).unwrap_or_default();
+    match tag.as_str() {
+        "length" => length(track),
+        "track" | "disc" => {
+            let number = value();
+            number.parse::<u64>().map(Cell::Number).unwrap_or(Cell::Text(number))
+        },
+        _ => Cell::Text(value()),
+    }
+}
+
+// the value of a tag as it is shown in the table
+pub fn tag_value(track: &Track, tag: &str) -> Option<String> {
+    match tag.to_lowercase().as_str() {
+        // these have placeholders like "unknown artist"
+        "title" => Some(track.get_title()),
+        "artist" => Some(track.get_artist()),
+        "album" => Some(track.get_album()),
+        "length" | "duration" => Some(length(track).text()),
+        "filename" => Path::new(&track.file).file_name().map(|n| n.to_string_lossy().into_owned()),
+        // "3/12" is shown as "3"
+        "track" | "disc" => track.get_tag(tag).map(|n| n.split('/').next().unwrap_or("").trim().to_string()),
+        "bitrate" => track.get_tag(tag).map(|b| format!("{} kbps", b)),
+        _ => track.get_tag(tag),
+    }
+}
+
+// the length of a track, streams have no length
+fn length(track: &Track) -> Cell {
+    if track.is_stream() && track.duration.is_none() {
+        Cell::Duration(None)
+    }
+    else {
+        Cell::Duration(Some(track.get_duration()))
+    }
+}
+
+// the title of a column, e.g. "albumartist" is shown as "Albumartist"
+pub fn title(column: &ColumnConfig) -> String {
+    if let Some(title) = &column.title {
+        return title.clone();
+    }
+
+    let name = column.tag.clone().unwrap_or_default();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
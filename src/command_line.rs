@@ -0,0 +1,411 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::columns::COLUMN_TAGS;
+use crate::sorting::SortKey;
+use crate::stations;
+use crate::util::paths::data_dir;
+
+// number of commands kept in the history file
+const HISTORY_LENGTH: usize = 500;
+
+// names of all commands, used for the completion
+pub const COMMAND_NAMES: [&str; 20] = [
+    "add", "addstation", "addto", "addurl", "clear", "filter", "load", "next", "pause", "play", "prev", "quit",
+    "rmstation", "save", "seek", "set", "sort", "stop", "toggle", "vol",
+];
+
+// tags that can be searched with add
+pub const TAGS: [&str; 8] = ["any", "artist", "album", "albumartist", "title", "genre", "composer", "date"];
+
+// playback options of set
+pub const OPTIONS: [&str; 3] = ["random", "repeat", "single"];
+
+// a command of the command line, also used by the keymap
+#[derive(Clone, PartialEq, Debug)]
+pub enum PromptCommand {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    Seek(String), // time like "1:30", relative with a leading + or -
+    Volume(String), // 0 - 100, relative with a leading + or -
+    Load(String), // playlist name
+    Add(String, String), // tag and value, adds all matching tracks to the queue
+    Save(String), // saves the queue as a playlist
+    Clear,
+    Set(String, Option<bool>), // option and new value, None toggles it
+    Sort(Vec<SortKey>), // sorts the main table, no keys restore the order of the server
+    Filter(String), // only shows matching rows, an empty filter shows all
+    AddTo(String), // adds the marked rows to a playlist
+    AddUrl(String, Option<String>), // stream url, added to the queue or the given playlist
+    AddStation(String, Option<String>), // stream url and name of a new favourite station
+    RemoveStation(String), // name or url of a favourite station
+    Quit,
+}
+
+// parses a command line like "seek 1:30" or "add artist "Miles Davis""
+pub fn parse(line: &str) -> Result<PromptCommand, String> {
+    let words: Vec<String> = split_words(line).into_iter().map(|(_, word)| word).collect();
+    let name = match words.first() {
+        Some(n) => n.to_lowercase(),
+        None => return Err(String::from("no command")),
+    };
+    // playlist names and tag values may contain spaces without quotes
+    let rest = |from: usize| words.iter().skip(from).cloned().collect::<Vec<String>>().join(" ");
+    let argument = |usage: &str| {
+        if words.len() < 2 {
+            Err(format!("usage: {}", usage))
+        }
+        else {
+            Ok(rest(1))
+        }
+    };
+
+    let command = match name.as_str() {
+        "play" => PromptCommand::Play,
+        "pause" => PromptCommand::Pause,
+        "toggle" => PromptCommand::Toggle,
+        "stop" => PromptCommand::Stop,
+        "next" => PromptCommand::Next,
+        "prev" | "previous" => PromptCommand::Previous,
+        "clear" => PromptCommand::Clear,
+        "filter" => PromptCommand::Filter(rest(1)),
+        "sort" => {
+            let keys: Result<Vec<SortKey>, String> = words.iter().skip(1).map(|w| SortKey::parse(w)).collect();
+            PromptCommand::Sort(keys?)
+        },
+        "quit" | "q" => PromptCommand::Quit,
+        "seek" => PromptCommand::Seek(argument("seek <[+-]time>")?),
+        "vol" | "volume" => PromptCommand::Volume(argument("vol <[+-]0-100>")?),
+        "load" => PromptCommand::Load(argument("load <playlist>")?),
+        "save" => PromptCommand::Save(argument("save <playlist>")?),
+        "addto" => PromptCommand::AddTo(argument("addto <playlist>")?),
+        "addurl" => {
+            let usage = "usage: addurl <http(s)://url> [playlist]";
+            match words.get(1) {
+                Some(url) if stations::is_stream_url(url) => {},
+                Some(url) => return Err(format!("\"{}\" is not a http(s) url, {}", url, usage)),
+                None => return Err(String::from(usage)),
+            }
+            let playlist = if words.len() > 2 { Some(rest(2)) } else { None };
+            PromptCommand::AddUrl(words[1].clone(), playlist)
+        },
+        "addstation" => {
+            let usage = "usage: addstation <http(s)://url> [name]";
+            match words.get(1) {
+                Some(url) if stations::is_stream_url(url) => {},
+                Some(url) => return Err(format!("\"{}\" is not a http(s) url, {}", url, usage)),
+                None => return Err(String::from(usage)),
+            }
+            let name = if words.len() > 2 { Some(rest(2)) } else { None };
+            PromptCommand::AddStation(words[1].clone(), name)
+        },
+        "rmstation" => PromptCommand::RemoveStation(argument("rmstation <name|url>")?),
+        "add" => {
+            if words.len() < 3 {
+                return Err(format!("usage: add <{}> <value>", TAGS.join("|")));
+            }
+            let tag = words[1].to_lowercase();
+            if !TAGS.contains(&tag.as_str()) {
+                return Err(format!("unknown tag \"{}\" (available: {})", words[1], TAGS.join(", ")));
+            }
+            PromptCommand::Add(tag, rest(2))
+        },
+        "set" => {
+            let usage = format!("usage: set <{}> <on|off|toggle>", OPTIONS.join("|"));
+            if words.len() != 3 {
+                return Err(usage);
+            }
+            let option = words[1].to_lowercase();
+            if !OPTIONS.contains(&option.as_str()) {
+                return Err(usage);
+            }
+            let value = match words[2].to_lowercase().as_str() {
+                "on" | "true" | "1" => Some(true),
+                "off" | "false" | "0" => Some(false),
+                "toggle" => None,
+                _ => return Err(usage),
+            };
+            PromptCommand::Set(option, value)
+        },
+        _ => return Err(format!("unknown command \"{}\"", name)),
+    };
+
+    Ok(command)
+}
+
+// splits a line into words, "double" or 'single' quotes keep spaces and \ escapes a character
+// returns the byte position where each word starts
+fn split_words(line: &str) -> Vec<(usize, String)> {
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+
+    while let Some((position, c)) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                let word = current.get_or_insert((position, String::new()));
+                if let Some((_, escaped)) = chars.next() {
+                    word.1.push(escaped);
+                }
+            },
+            ('"', None) | ('\'', None) => {
+                current.get_or_insert((position, String::new()));
+                quote = Some(c);
+            },
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if let Some(word) = current.take() {
+                    words.push(word);
+                }
+            },
+            (c, _) => current.get_or_insert((position, String::new())).1.push(c),
+        }
+    }
+
+    if let Some(word) = current {
+        words.push(word);
+    }
+
+    words
+}
+
+// values the completion needs from the player
+pub trait CompletionSource {
+    fn playlist_names(&mut self) -> Vec<String>;
+    fn tag_values(&mut self, tag: &str) -> Vec<String>;
+}
+
+// state of the ':' prompt at the bottom of the screen, also used for the '/' filter
+pub struct CommandLine {
+    pub active: bool,
+    pub prompt: char, // ':' runs commands, '/' filters the main table
+    pub input: String,
+    pub cursor: usize, // position of the cursor in characters
+    history: Vec<String>,
+    history_position: Option<usize>, // entry of the history that is shown
+    completions: Vec<String>, // candidates of the last tab press
+    completion_index: usize,
+    completion_start: usize, // byte position of the completed word in the input
+}
+
+impl CommandLine {
+    pub fn new() -> CommandLine {
+        CommandLine {
+            active: false,
+            prompt: ':',
+            input: String::new(),
+            cursor: 0,
+            history: load_history(),
+            history_position: None,
+            completions: Vec::new(),
+            completion_index: 0,
+            completion_start: 0,
+        }
+    }
+
+    pub fn open(&mut self, prompt: char, input: &str) {
+        self.active = true;
+        self.prompt = prompt;
+        self.set_input(input.to_string());
+        self.history_position = None;
+    }
+
+    // the filter has no history and no completion
+    pub fn is_filter(&self) -> bool {
+        self.prompt == '/'
+    }
+
+    // edits the input, returns the line when enter is pressed
+    pub fn handle_key(&mut self, key: KeyEvent, source: &mut dyn CompletionSource) -> Option<String> {
+        if key.code != KeyCode::Tab {
+            self.completions.clear();
+        }
+
+        let position = self.byte_position(self.cursor);
+        match key.code {
+            KeyCode::Enter => {
+                self.active = false;
+                let line = self.input.trim().to_string();
+                if line.is_empty() {
+                    return None;
+                }
+                if !self.is_filter() {
+                    self.add_to_history(&line);
+                }
+                return Some(line);
+            },
+            KeyCode::Esc => self.active = false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.active = false,
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => self.set_input(String::new()),
+            KeyCode::Char(c) => {
+                self.input.insert(position, c);
+                self.cursor += 1;
+            },
+            KeyCode::Backspace => {
+                // like in vim an empty prompt is closed
+                if self.input.is_empty() {
+                    self.active = false;
+                }
+                else if self.cursor > 0 {
+                    self.cursor -= 1;
+                    let previous = self.byte_position(self.cursor);
+                    self.input.remove(previous);
+                }
+            },
+            KeyCode::Delete if position < self.input.len() => {
+                self.input.remove(position);
+            },
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Up if !self.is_filter() => self.browse_history(true),
+            KeyCode::Down if !self.is_filter() => self.browse_history(false),
+            KeyCode::Tab if !self.is_filter() => self.complete(source),
+            _ => {},
+        }
+
+        None
+    }
+
+    // replaces the word before the cursor with the next candidate
+    fn complete(&mut self, source: &mut dyn CompletionSource) {
+        if self.completions.is_empty() {
+            let (start, candidates) = candidates(&self.input, source);
+            if candidates.is_empty() {
+                return;
+            }
+            self.completions = candidates;
+            self.completion_index = 0;
+            self.completion_start = start;
+        }
+        else {
+            self.completion_index = (self.completion_index + 1) % self.completions.len();
+        }
+
+        let mut candidate = self.completions[self.completion_index].clone();
+        if candidate.contains(' ') {
+            candidate = format!("\"{}\"", candidate.replace('"', "\\\""));
+        }
+        let prefix = self.input[..self.completion_start].to_string();
+        self.set_input(prefix + &candidate);
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let position = match (self.history_position, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(p), true) => Some(p.saturating_sub(1)),
+            (Some(p), false) if p + 1 < self.history.len() => Some(p + 1),
+            (Some(_), false) => None,
+        };
+
+        self.history_position = position;
+        let line = position.map(|p| self.history[p].clone()).unwrap_or_default();
+        self.set_input(line);
+    }
+
+    fn add_to_history(&mut self, line: &str) {
+        if self.history.last().map(|l| l.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.drain(..self.history.len() - HISTORY_LENGTH);
+        }
+
+        save_history(&self.history);
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.cursor = input.chars().count();
+        self.input = input;
+    }
+
+    fn byte_position(&self, cursor: usize) -> usize {
+        self.input.char_indices().nth(cursor).map(|(i, _)| i).unwrap_or(self.input.len())
+    }
+}
+
+// finds the candidates for the word at the end of the input
+// returns the byte position where the completed text starts
+fn candidates(input: &str, source: &mut dyn CompletionSource) -> (usize, Vec<String>) {
+    let words = split_words(input);
+    let ends_with_space = input.chars().last().map(|c| c.is_whitespace()).unwrap_or(true);
+
+    // index of the word that is completed
+    let index = if ends_with_space { words.len() } else { words.len() - 1 };
+    let command = words.first().map(|(_, w)| w.to_lowercase()).unwrap_or_default();
+    let tag = words.get(1).map(|(_, w)| w.to_lowercase()).unwrap_or_default();
+
+    // playlist names and tag values are the rest of the line and can contain spaces
+    let rest_from = match command.as_str() {
+        "load" | "save" | "addto" | "rmstation" => Some(1),
+        "add" | "addurl" => Some(2),
+        _ => None,
+    };
+
+    let (start, partial) = match rest_from {
+        Some(from) if index >= from && words.len() > from => {
+            let start = words[from].0;
+            (start, input[start..].replace(['"', '\''], ""))
+        },
+        _ if ends_with_space => (input.len(), String::new()),
+        _ => (words[index].0, words[index].1.clone()),
+    };
+
+    let options: Vec<String> = match (index, command.as_str()) {
+        (0, _) => COMMAND_NAMES.iter().map(|s| s.to_string()).collect(),
+        (1, "load") | (1, "save") | (1, "addto") => source.playlist_names(),
+        (1, "add") => TAGS.iter().map(|s| s.to_string()).collect(),
+        (1, "set") => OPTIONS.iter().map(|s| s.to_string()).collect(),
+        (2, "set") => vec![String::from("on"), String::from("off"), String::from("toggle")],
+        (_, "sort") => COLUMN_TAGS.iter().map(|s| s.to_string()).collect(),
+        (_, "load") | (_, "save") | (_, "addto") => source.playlist_names(),
+        (_, "add") if index >= 2 => source.tag_values(&tag),
+        (_, "addurl") if index >= 2 => source.playlist_names(),
+        (_, "rmstation") => stations::load_stations().into_iter().map(|station| station.name).collect(),
+        _ => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    let mut candidates: Vec<String> = options.into_iter().filter(|o| o.to_lowercase().starts_with(&partial)).collect();
+    candidates.sort_by_key(|c| c.to_lowercase());
+    candidates.dedup();
+
+    (start, candidates)
+}
+
+fn history_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("command_history"))
+}
+
+fn load_history() -> Vec<String> {
+    match history_file().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(content) => content.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect(),
+        None => Vec::new(),
+    }
+}
+
+// the history is written after every command, so it survives crashes
+fn save_history(history: &[String]) {
+    let path = match history_file() {
+        Some(p) => p,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, history.join("\n") + "\n");
+}
@@ -0,0 +1,654 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::KeyEvent;
+use serde::Deserialize;
+use tui::style::Color;
+
+use crate::columns::COLUMN_TAGS;
+use crate::command_line;
+use crate::hooks::{EventHooks, PlayerEvent};
+use crate::keymap::{Action, Keymap, PRESET_NAMES, parse_sequence};
+use crate::scrobbler::{self, Service};
+use crate::sorting::{SortKey, TableOrder, FILTER_MODES};
+use crate::util::app::CmdArgs;
+use crate::util::paths::config_dir;
+use crate::util::theme::{Theme, ColorDepth, THEME_NAMES, UNICODE_GLYPHS, ASCII_GLYPHS, detect_color_depth, detect_unicode};
+
+// +----------------------------------------------------------------------+
+// | the configuration is read from $XDG_CONFIG_HOME/rust-cmp/config.toml |
+// | every key is optional, missing keys use the defaults below           |
+// | command line options override the values of the file                 |
+// +----------------------------------------------------------------------+
+
+// environment variables for the scrobbling secrets, they are not accepted on the command
+// line as other users could read them from the process list
+const SECRET_VARIABLES: [&str; 3] = ["RUST_CMP_LISTENBRAINZ_TOKEN", "RUST_CMP_LASTFM_API_SECRET", "RUST_CMP_LASTFM_SESSION_KEY"];
+
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub ui: UiConfig,
+    pub theme: ThemeConfig,
+    pub colors: ColorConfig,
+    pub keys: KeyConfig,
+    pub behaviour: BehaviourConfig,
+    pub scrobbling: ScrobblingConfig,
+    pub hooks: HooksConfig,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub ip: String,
+    pub port: u16,
+    pub stream_port: u16, // port of the httpd output
+    pub stream_url: Option<String>, // overrides the url built from ip and stream port
+    pub music_dir: Option<String>, // play local files instead of connecting to mpd
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            ip: String::from("127.0.0.1"),
+            port: 6600,
+            stream_port: 8000,
+            stream_url: None,
+            music_dir: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub tick_rate: u64, // time in ms between two ticks
+    pub horizontal_scroll_delay: u16, // ticks before a long entry starts scrolling
+    pub sidebar_width: u16, // in percent of the terminal width
+    pub playbar_widths: Vec<u16>, // track name, artist name and timeline in percent
+    pub columns: Vec<ColumnConfig>, // columns of the main table, used by all views that list tracks
+    pub footer: bool, // shows the most relevant keys at the bottom
+    pub sort: Vec<String>, // tags the tables are sorted by with the collation of the locale, "-date" sorts descending, empty keeps the server order
+    pub sort_ignore_the: bool, // "The Beatles" is sorted as "Beatles"
+    pub filter_mode: String, // "substring" or "fuzzy"
+}
+
+impl Default for UiConfig {
+    fn default() -> UiConfig {
+        UiConfig {
+            tick_rate: 250,
+            horizontal_scroll_delay: 1,
+            sidebar_width: 20,
+            playbar_widths: vec![25, 25, 50],
+            columns: vec![
+                ColumnConfig::new("Track", "title", 40, 0),
+                ColumnConfig::new("Artist", "artist", 25, 50),
+                ColumnConfig::new("Album", "album", 25, 90),
+                ColumnConfig::new("Length", "length", 10, 0),
+            ],
+            footer: true,
+            sort: Vec::new(),
+            sort_ignore_the: true,
+            filter_mode: String::from("substring"),
+        }
+    }
+}
+
+// a column of the main table, shows a tag or a format template like "[%track%. ]%title%"
+//   [[ui.columns]]
+//   title = "Album"
+//   tag = "album"
+//   width = 25
+//   hide_below = 90
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnConfig {
+    pub title: Option<String>, // the tag is used if there is no title
+    pub tag: Option<String>, // one of columns::COLUMN_TAGS
+    pub format: Option<String>, // template with %tag% placeholders, see template.rs
+    pub width: u16, // relative to the widths of the other columns
+    #[serde(default)]
+    pub hide_below: u16, // the column is hidden if the table is narrower
+}
+
+impl ColumnConfig {
+    fn new(title: &str, tag: &str, width: u16, hide_below: u16) -> ColumnConfig {
+        ColumnConfig {
+            title: Some(String::from(title)),
+            tag: Some(String::from(tag)),
+            format: None,
+            width,
+            hide_below,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: String, // one of THEME_NAMES
+    pub glyphs: String, // "auto", "unicode" or "ascii"
+    pub color_mode: String, // "auto", "truecolor", "256", "16" or "none"
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        ThemeConfig {
+            name: String::from("dark"),
+            glyphs: String::from("auto"),
+            color_mode: String::from("auto"),
+        }
+    }
+}
+
+// overrides for single colours of the theme
+// colours are "#rrggbb", a name like "lightblue" or a number of the 256 colour palette
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    pub border: Option<String>,
+    pub header: Option<String>,
+    pub title: Option<String>,
+    pub selection: Option<String>,
+    pub playing: Option<String>,
+    pub timeline: Option<String>,
+    pub timeline_selected: Option<String>,
+    pub status: Option<String>,
+}
+
+impl ColorConfig {
+    // the configured colours with their keys and the matching colour of the theme
+    fn overrides<'t>(&self, theme: &'t mut Theme) -> Vec<(&'static str, &Option<String>, &'t mut Color)> {
+        vec![
+            ("colors.border", &self.border, &mut theme.border),
+            ("colors.header", &self.header, &mut theme.header),
+            ("colors.title", &self.title, &mut theme.title),
+            ("colors.selection", &self.selection, &mut theme.selection),
+            ("colors.playing", &self.playing, &mut theme.playing),
+            ("colors.timeline", &self.timeline, &mut theme.gauge),
+            ("colors.timeline_selected", &self.timeline_selected, &mut theme.gauge_selected),
+            ("colors.status", &self.status, &mut theme.status),
+        ]
+    }
+}
+
+// the bindings of a preset, single actions can be bound to other keys
+// keys are a character or a name like "enter", optionally with "ctrl-" or "alt-",
+// sequences are separated by spaces, e.g. "g g"
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyConfig {
+    pub preset: String,
+    pub bind: BTreeMap<String, KeyList>,
+    pub commands: BTreeMap<String, String>, // key sequence and command line, e.g. "ctrl-j" = "seek +30"
+}
+
+impl Default for KeyConfig {
+    fn default() -> KeyConfig {
+        KeyConfig {
+            preset: String::from("default"),
+            bind: BTreeMap::new(),
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+// an action can be bound to one key sequence or a list of them
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn sequences(&self) -> Vec<String> {
+        match self {
+            KeyList::One(keys) => vec![keys.clone()],
+            KeyList::Many(list) => list.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviourConfig {
+    pub mpris: bool,
+    pub notify: bool,
+    pub notify_unfocused: bool,
+}
+
+impl Default for BehaviourConfig {
+    fn default() -> BehaviourConfig {
+        BehaviourConfig {
+            mpris: true,
+            notify: false,
+            notify_unfocused: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrobblingConfig {
+    pub listenbrainz_token: Option<String>,
+    pub listenbrainz_url: String,
+    pub lastfm_api_key: Option<String>,
+    pub lastfm_api_secret: Option<String>,
+    pub lastfm_session_key: Option<String>,
+    pub lastfm_url: String,
+}
+
+impl Default for ScrobblingConfig {
+    fn default() -> ScrobblingConfig {
+        ScrobblingConfig {
+            listenbrainz_token: None,
+            listenbrainz_url: String::from(scrobbler::LISTENBRAINZ_URL),
+            lastfm_api_key: None,
+            lastfm_api_secret: None,
+            lastfm_session_key: None,
+            lastfm_url: String::from(scrobbler::LASTFM_URL),
+        }
+    }
+}
+
+// shell commands that are run on player events
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub on_song_change: Vec<String>,
+    pub on_play: Vec<String>,
+    pub on_pause: Vec<String>,
+    pub on_stop: Vec<String>,
+    pub on_queue_end: Vec<String>,
+    pub on_connection_loss: Vec<String>,
+}
+
+impl Config {
+    // the file given on the command line or the default location
+    pub fn path(cli: &CmdArgs) -> Option<PathBuf> {
+        match &cli.config {
+            Some(path) => Some(PathBuf::from(path)),
+            None => config_dir().map(|dir| dir.join("config.toml")),
+        }
+    }
+
+    // reads and validates the configuration and applies the command line options
+    // a missing file is not an error, the defaults are used instead
+    pub fn load(cli: &CmdArgs) -> Result<Config, String> {
+        let mut config = match Config::path(cli) {
+            Some(path) if path.exists() => Config::read(&path)?,
+            _ => Config::default(),
+        };
+
+        config.apply_environment();
+        config.apply_args(cli);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Config, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+        // unknown keys and wrong types are reported with their position by toml
+        toml::from_str(&content).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+
+    // the secrets in the environment take precedence over the file
+    fn apply_environment(&mut self) {
+        let scrobbling = &mut self.scrobbling;
+        let secrets = vec![&mut scrobbling.listenbrainz_token, &mut scrobbling.lastfm_api_secret, &mut scrobbling.lastfm_session_key];
+        for (value, variable) in secrets.into_iter().zip(SECRET_VARIABLES.iter()) {
+            match env::var(variable) {
+                Ok(secret) if !secret.is_empty() => *value = Some(secret),
+                _ => {},
+            }
+        }
+    }
+
+    // command line options take precedence over the file
+    fn apply_args(&mut self, cli: &CmdArgs) {
+        if let Some(ip) = &cli.ip {
+            self.server.ip = ip.clone();
+        }
+        if let Some(port) = cli.port {
+            self.server.port = port;
+        }
+        if let Some(stream_port) = cli.stream_port {
+            self.server.stream_port = stream_port;
+        }
+        if cli.stream_url.is_some() {
+            self.server.stream_url = cli.stream_url.clone();
+        }
+        if cli.local.is_some() {
+            self.server.music_dir = cli.local.clone();
+        }
+        if let Some(tick_rate) = cli.tick_rate {
+            self.ui.tick_rate = tick_rate;
+        }
+
+        if cli.no_mpris {
+            self.behaviour.mpris = false;
+        }
+        if cli.notify || cli.notify_unfocused {
+            self.behaviour.notify = true;
+        }
+        if cli.notify_unfocused {
+            self.behaviour.notify_unfocused = true;
+        }
+
+        let scrobbling = &mut self.scrobbling;
+        if cli.lastfm_api_key.is_some() {
+            scrobbling.lastfm_api_key = cli.lastfm_api_key.clone();
+        }
+        if let Some(url) = &cli.listenbrainz_url {
+            scrobbling.listenbrainz_url = url.clone();
+        }
+        if let Some(url) = &cli.lastfm_url {
+            scrobbling.lastfm_url = url.clone();
+        }
+
+        // hooks from the command line are run in addition to the configured ones
+        self.hooks.on_song_change.extend(cli.on_song_change.iter().cloned());
+        self.hooks.on_play.extend(cli.on_play.iter().cloned());
+        self.hooks.on_pause.extend(cli.on_pause.iter().cloned());
+        self.hooks.on_stop.extend(cli.on_stop.iter().cloned());
+        self.hooks.on_queue_end.extend(cli.on_queue_end.iter().cloned());
+        self.hooks.on_connection_loss.extend(cli.on_connection_loss.iter().cloned());
+    }
+
+    // checks the values that toml can not check, all problems are reported at once
+    fn validate(&self) -> Result<(), String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        if self.ui.tick_rate == 0 {
+            errors.push(String::from("ui.tick_rate: has to be greater than 0"));
+        }
+        if self.ui.sidebar_width < 5 || self.ui.sidebar_width > 60 {
+            errors.push(format!("ui.sidebar_width: {} is not between 5 and 60", self.ui.sidebar_width));
+        }
+        check_widths("ui.playbar_widths", &self.ui.playbar_widths, 3, &mut errors);
+        for key in self.ui.sort.iter() {
+            if let Err(message) = SortKey::parse(key) {
+                errors.push(format!("ui.sort: {}", message));
+            }
+        }
+        if !FILTER_MODES.contains(&self.ui.filter_mode.as_str()) {
+            errors.push(format!("ui.filter_mode: unknown mode \"{}\" (available: {})", self.ui.filter_mode, FILTER_MODES.join(", ")));
+        }
+        if self.ui.columns.is_empty() {
+            errors.push(String::from("ui.columns: at least one column is needed"));
+        }
+        for (i, column) in self.ui.columns.iter().enumerate() {
+            let key = format!("ui.columns[{}]", i);
+            match (&column.tag, &column.format) {
+                (Some(tag), None) if !COLUMN_TAGS.contains(&tag.to_lowercase().as_str()) => {
+                    errors.push(format!("{}.tag: unknown tag \"{}\" (available: {})", key, tag, COLUMN_TAGS.join(", ")));
+                },
+                (None, None) => errors.push(format!("{}: needs a tag or a format", key)),
+                (Some(_), Some(_)) => errors.push(format!("{}: tag and format can not be used together", key)),
+                _ => {},
+            }
+            if column.width == 0 {
+                errors.push(format!("{}.width: has to be greater than 0", key));
+            }
+        }
+
+        if Theme::by_name(&self.theme.name).is_none() {
+            errors.push(format!("theme.name: unknown theme \"{}\" (available: {})", self.theme.name, THEME_NAMES.join(", ")));
+        }
+        if !["auto", "unicode", "ascii"].contains(&self.theme.glyphs.as_str()) {
+            errors.push(format!("theme.glyphs: \"{}\" is not one of auto, unicode, ascii", self.theme.glyphs));
+        }
+        if !["auto", "truecolor", "256", "16", "none"].contains(&self.theme.color_mode.as_str()) {
+            errors.push(format!("theme.color_mode: \"{}\" is not one of auto, truecolor, 256, 16, none", self.theme.color_mode));
+        }
+
+        for (key, value, _) in self.colors.overrides(&mut Theme::dark()) {
+            if let Some(value) = value {
+                if parse_color(value).is_none() {
+                    errors.push(format!("{}: \"{}\" is not a colour (use \"#rrggbb\", a name like \"lightblue\" or 0-255)", key, value));
+                }
+            }
+        }
+
+        if let Err(key_errors) = self.keymap() {
+            errors.extend(key_errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(format!("invalid config:\n  {}", errors.join("\n  ")))
+        }
+    }
+
+    // the preset with the configured bindings
+    // returns all unknown actions, invalid keys and conflicting bindings
+    pub fn keymap(&self) -> Result<Keymap, Vec<String>> {
+        let mut errors: Vec<String> = Vec::new();
+
+        let mut keymap = match Keymap::preset(&self.keys.preset) {
+            Some(k) => k,
+            None => {
+                errors.push(format!("keys.preset: unknown preset \"{}\" (available: {})", self.keys.preset, PRESET_NAMES.join(", ")));
+                Keymap::preset("default").unwrap()
+            },
+        };
+
+        let mut bindings: Vec<(Action, Vec<Vec<KeyEvent>>)> = Vec::new();
+        for (name, list) in self.keys.bind.iter() {
+            let action = match Action::from_name(name) {
+                Some(a) => a,
+                None => {
+                    errors.push(format!("keys.bind.{}: unknown action", name));
+                    continue;
+                },
+            };
+
+            let mut sequences: Vec<Vec<KeyEvent>> = Vec::new();
+            for value in list.sequences() {
+                match parse_sequence(&value) {
+                    Some(keys) => sequences.push(keys),
+                    None => errors.push(format!("keys.bind.{}: \"{}\" is not a key (use a character or a name like \"enter\", optionally with \"ctrl-\" or \"alt-\")", name, value)),
+                }
+            }
+            bindings.push((action, sequences));
+        }
+
+        keymap.rebind(&bindings);
+
+        for (keys, line) in self.keys.commands.iter() {
+            let sequence = match parse_sequence(keys) {
+                Some(s) => s,
+                None => {
+                    errors.push(format!("keys.commands: \"{}\" is not a key sequence", keys));
+                    continue;
+                },
+            };
+            match command_line::parse(line) {
+                Ok(_) => keymap.bind_command(sequence, line.clone()),
+                Err(message) => errors.push(format!("keys.commands.\"{}\": {}", keys, message)),
+            }
+        }
+
+        for conflict in keymap.conflicts() {
+            errors.push(format!("keys: {}", conflict));
+        }
+
+        if errors.is_empty() {
+            Ok(keymap)
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    // the sort and filter settings of the tables, the filter itself is entered with '/'
+    pub fn table_order(&self) -> TableOrder {
+        TableOrder {
+            sort: self.ui.sort.iter().filter_map(|key| SortKey::parse(key).ok()).collect(),
+            ignore_the: self.ui.sort_ignore_the,
+            filter: String::new(),
+            fuzzy: self.ui.filter_mode == "fuzzy",
+        }
+    }
+
+    // the configured theme with the colour overrides, reduced to what the terminal can show
+    pub fn theme(&self) -> Theme {
+        let mut theme = Theme::by_name(&self.theme.name).unwrap_or(Theme::dark());
+
+        for (_, value, color) in self.colors.overrides(&mut theme) {
+            if let Some(parsed) = value.as_ref().and_then(|v| parse_color(v)) {
+                *color = parsed;
+            }
+        }
+
+        let unicode = match self.theme.glyphs.as_str() {
+            "unicode" => true,
+            "ascii" => false,
+            _ => detect_unicode(),
+        };
+        theme.glyphs = if unicode { UNICODE_GLYPHS } else { ASCII_GLYPHS };
+
+        let depth = match self.theme.color_mode.as_str() {
+            "truecolor" => ColorDepth::TrueColor,
+            "256" => ColorDepth::Ansi256,
+            "16" => ColorDepth::Ansi16,
+            "none" => ColorDepth::NoColor,
+            _ => detect_color_depth(),
+        };
+
+        // NO_COLOR also wins over a configured colour mode
+        if detect_color_depth() == ColorDepth::NoColor {
+            theme.downgrade(ColorDepth::NoColor)
+        }
+        else {
+            theme.downgrade(depth)
+        }
+    }
+
+    // the scrobbling services that have credentials
+    pub fn scrobble_services(&self) -> Vec<Service> {
+        let scrobbling = &self.scrobbling;
+        let mut services: Vec<Service> = Vec::new();
+
+        if let Some(token) = &scrobbling.listenbrainz_token {
+            services.push(Service::ListenBrainz {
+                url: scrobbling.listenbrainz_url.clone(),
+                token: token.clone(),
+            });
+        }
+
+        if let (Some(api_key), Some(api_secret), Some(session_key)) = (&scrobbling.lastfm_api_key, &scrobbling.lastfm_api_secret, &scrobbling.lastfm_session_key) {
+            services.push(Service::LastFm {
+                url: scrobbling.lastfm_url.clone(),
+                api_key: api_key.clone(),
+                api_secret: api_secret.clone(),
+                session_key: session_key.clone(),
+            });
+        }
+
+        services
+    }
+
+    pub fn event_hooks(&self) -> EventHooks {
+        let hooks = vec![
+            (PlayerEvent::SongChange, &self.hooks.on_song_change),
+            (PlayerEvent::Play, &self.hooks.on_play),
+            (PlayerEvent::Pause, &self.hooks.on_pause),
+            (PlayerEvent::Stop, &self.hooks.on_stop),
+            (PlayerEvent::QueueEnd, &self.hooks.on_queue_end),
+            (PlayerEvent::ConnectionLoss, &self.hooks.on_connection_loss),
+        ];
+
+        let mut commands: Vec<(PlayerEvent, String)> = Vec::new();
+        for (event, hook) in hooks {
+            for command in hook.iter() {
+                commands.push((event, command.clone()));
+            }
+        }
+
+        EventHooks::new(commands)
+    }
+}
+
+// the widths have to add up to 100 percent
+fn check_widths(key: &str, widths: &[u16], count: usize, errors: &mut Vec<String>) {
+    if widths.len() != count {
+        errors.push(format!("{}: expected {} widths, found {}", key, count, widths.len()));
+    }
+    else {
+        // summed as u32, large widths would overflow a u16
+        let sum: u32 = widths.iter().map(|w| *w as u32).sum();
+        if sum != 100 {
+            errors.push(format!("{}: the widths add up to {} instead of 100", key, sum));
+        }
+    }
+}
+
+// parses "#rrggbb", a colour name or a number of the 256 colour palette
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        // checked before slicing, a multi-byte character would split inside it
+        if hex.chars().count() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(Color::Rgb(component(0)?, component(2)?, component(4)?));
+    }
+
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Color::Indexed(index));
+    }
+
+    match value.replace(['_', '-', ' '], "").as_str() {
+        "reset" => Some(Color::Reset),
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "gray" | "grey" => Some(Color::Gray),
+        "darkgray" | "darkgrey" => Some(Color::DarkGray),
+        "lightred" => Some(Color::LightRed),
+        "lightgreen" => Some(Color::LightGreen),
+        "lightyellow" => Some(Color::LightYellow),
+        "lightblue" => Some(Color::LightBlue),
+        "lightmagenta" => Some(Color::LightMagenta),
+        "lightcyan" => Some(Color::LightCyan),
+        "white" => Some(Color::White),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_widths_are_reported() {
+        let mut errors = Vec::new();
+        check_widths("ui.playbar_widths", &[60000, 6000], 2, &mut errors);
+        assert_eq!(errors, vec![String::from("ui.playbar_widths: the widths add up to 66000 instead of 100")]);
+    }
+
+    #[test]
+    fn hex_colors_need_six_hex_digits() {
+        assert!(parse_color("#1a2B3c") == Some(Color::Rgb(0x1a, 0x2b, 0x3c)));
+        assert!(parse_color("#12345").is_none());
+        assert!(parse_color("#+1+2+3").is_none());
+        // six bytes but multi-byte characters
+        assert!(parse_color("#ü1234").is_none());
+        assert!(parse_color("#aéaa").is_none());
+    }
+}
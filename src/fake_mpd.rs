@@ -0,0 +1,696 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+#[cfg(all(unix, test))]
+use std::os::unix::net::UnixListener;
+#[cfg(all(unix, test))]
+use std::path::Path;
+
+use crate::backend::{Track, PlaybackState};
+#[cfg(test)]
+use crate::memory_player::demo_playlists;
+#[cfg(test)]
+use crate::player::Player;
+
+// protocol version announced to clients
+const PROTOCOL_VERSION: &str = "0.21.0";
+
+// everything the fake server knows about
+// the fields can be changed at any time to script the server
+pub struct FakeState {
+    pub library: Vec<Track>,
+    pub playlists: Vec<(String, Vec<Track>)>,
+    pub queue: Vec<Track>,
+    pub current: Option<usize>, // position of the current song in the queue
+    pub state: PlaybackState,
+    pub volume: i8,
+    pub elapsed: f64, // elapsed time of the current song, does not advance by itself
+    pub repeat: bool,
+    pub random: bool,
+    pub single: bool,
+    pub consume: bool,
+    pub queue_version: u32,
+    pub commands: Vec<String>, // every command line received, in order
+    pub events: Vec<String>, // subsystems changed by the commands, in order, see idle
+}
+
+// a minimal in-process MPD server used for testing the client without a real MPD
+// all connections share the same state
+#[derive(Clone)]
+pub struct FakeMpd {
+    state: Arc<Mutex<FakeState>>,
+    changed: Arc<Condvar>, // wakes up the idle connections when events are added
+}
+
+impl FakeMpd {
+    // create a server with the given playlists, the library is made of all tracks in them
+    pub fn new(playlists: Vec<(String, Vec<Track>)>) -> FakeMpd {
+        let mut library: Vec<Track> = Vec::new();
+        for (_, tracks) in &playlists {
+            for track in tracks {
+                if !library.iter().any(|t| t.file == track.file) {
+                    library.push(track.clone());
+                }
+            }
+        }
+
+        FakeMpd {
+            state: Arc::new(Mutex::new(FakeState {
+                library,
+                playlists,
+                queue: Vec::new(),
+                current: None,
+                state: PlaybackState::Stop,
+                volume: 100,
+                elapsed: 0.0,
+                repeat: false,
+                random: false,
+                single: false,
+                consume: false,
+                queue_version: 1,
+                commands: Vec::new(),
+                events: Vec::new(),
+            })),
+            changed: Arc::new(Condvar::new()),
+        }
+    }
+
+    // listens on the given address and serves every connection in its own thread
+    // returns the bound address, so port 0 can be used to get a free port
+    pub fn listen_tcp<A: ToSocketAddrs>(&self, address: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let local_address = listener.local_addr()?;
+        let server = self.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || {
+                    if let Ok(reader) = stream.try_clone() {
+                        let _ = server.serve(BufReader::new(reader), stream);
+                    }
+                });
+            }
+        });
+
+        Ok(local_address)
+    }
+
+    // listens on a unix socket and serves every connection in its own thread
+    // only used by the tests, the client connects over tcp
+    #[cfg(all(unix, test))]
+    pub fn listen_unix<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let listener = UnixListener::bind(path)?;
+        let server = self.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || {
+                    if let Ok(reader) = stream.try_clone() {
+                        let _ = server.serve(BufReader::new(reader), stream);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    // gives access to the state of the server, e.g. to change the status between requests
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    // returns all commands received so far
+    #[cfg(test)]
+    pub fn received_commands(&self) -> Vec<String> {
+        self.state().commands.clone()
+    }
+
+    // handles one client connection until it is closed
+    fn serve<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "OK MPD {}", PROTOCOL_VERSION)?;
+
+        // commands of a command list are collected and executed at the end of the list
+        let mut command_list: Option<Vec<String>> = None;
+        let mut list_ok = false;
+
+        // idle only reports the events this connection has not seen yet
+        let mut seen_events = self.state().events.len();
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.trim() == "idle" || line.trim().starts_with("idle ") {
+                let response = self.idle(&line, &mut seen_events) + "OK\n";
+                writer.write_all(response.as_bytes())?;
+                continue;
+            }
+
+            match line.trim() {
+                "command_list_begin" => { command_list = Some(Vec::new()); list_ok = false; continue; },
+                "command_list_ok_begin" => { command_list = Some(Vec::new()); list_ok = true; continue; },
+                "command_list_end" => {
+                    let mut response = String::new();
+                    let mut failed = false;
+                    for (i, command) in command_list.take().unwrap_or_default().iter().enumerate() {
+                        match self.execute(command) {
+                            Ok(part) => {
+                                response.push_str(&part);
+                                if list_ok {
+                                    response.push_str("list_OK\n");
+                                }
+                            },
+                            Err(error) => {
+                                response.push_str(&ack(i, command, &error));
+                                failed = true;
+                                break;
+                            },
+                        }
+                    }
+                    if !failed {
+                        response.push_str("OK\n");
+                    }
+                    writer.write_all(response.as_bytes())?;
+                    continue;
+                },
+                "close" => {
+                    self.state().commands.push(line.clone());
+                    return Ok(());
+                },
+                _ => {},
+            }
+
+            if let Some(list) = command_list.as_mut() {
+                list.push(line);
+                continue;
+            }
+
+            let response = match self.execute(&line) {
+                Ok(response) => response + "OK\n",
+                Err(error) => ack(0, &line, &error),
+            };
+            writer.write_all(response.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    // blocks until one of the given subsystems (or any, if none is given) changed
+    // and returns the changed subsystems, noidle is not supported while waiting
+    fn idle(&self, line: &str, seen_events: &mut usize) -> String {
+        let arguments = parse_arguments(line);
+        let mut state = self.state();
+        state.commands.push(line.to_string());
+
+        loop {
+            let mut changed: Vec<String> = Vec::new();
+            for subsystem in state.events[*seen_events..].iter() {
+                if (arguments.len() == 1 || arguments[1..].contains(subsystem)) && !changed.contains(subsystem) {
+                    changed.push(subsystem.clone());
+                }
+            }
+            *seen_events = state.events.len();
+
+            if !changed.is_empty() {
+                return changed.iter().map(|subsystem| format!("changed: {}\n", subsystem)).collect();
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    // executes a single command and returns the response without the final "OK"
+    fn execute(&self, line: &str) -> Result<String, String> {
+        let mut state = self.state();
+        state.commands.push(line.to_string());
+
+        let arguments = parse_arguments(line);
+        let command = match arguments.first() {
+            Some(c) => c.as_str(),
+            None => return Err(String::from("No command given")),
+        };
+        let argument = |i: usize| arguments.get(i).map(|a| a.as_str()).unwrap_or("");
+
+        let mut response = String::new();
+
+        match command {
+            "ping" | "noidle" => {},
+            "status" => {
+                response.push_str(&format!("volume: {}\n", state.volume));
+                response.push_str(&format!("repeat: {}\n", state.repeat as u8));
+                response.push_str(&format!("random: {}\n", state.random as u8));
+                response.push_str(&format!("single: {}\n", state.single as u8));
+                response.push_str(&format!("consume: {}\n", state.consume as u8));
+                response.push_str(&format!("playlist: {}\n", state.queue_version));
+                response.push_str(&format!("playlistlength: {}\n", state.queue.len()));
+                response.push_str(&format!("state: {}\n", match state.state {
+                    PlaybackState::Play => "play",
+                    PlaybackState::Pause => "pause",
+                    PlaybackState::Stop => "stop",
+                }));
+
+                if let Some(pos) = state.current {
+                    let duration = state.queue[pos].duration.unwrap_or(0);
+                    response.push_str(&format!("song: {}\nsongid: {}\n", pos, pos + 1));
+                    if state.state != PlaybackState::Stop {
+                        response.push_str(&format!("time: {}:{}\n", state.elapsed as u64, duration));
+                        response.push_str(&format!("elapsed: {:.3}\n", state.elapsed));
+                        response.push_str(&format!("duration: {:.3}\n", duration as f64));
+                    }
+                }
+            },
+            // the client asks for it together with the status
+            "replay_gain_status" => response.push_str("replay_gain_mode: off\n"),
+            "currentsong" => {
+                if let Some(pos) = state.current {
+                    response.push_str(&song_response(&state.queue[pos], Some(pos)));
+                }
+            },
+            "play" | "playid" => {
+                if state.queue.is_empty() {
+                    return Err(String::from("No songs in queue"));
+                }
+                let pos = match arguments.get(1).and_then(|a| a.parse::<usize>().ok()) {
+                    Some(p) if command == "playid" => p.saturating_sub(1),
+                    Some(p) => p,
+                    None => state.current.unwrap_or(0),
+                };
+                if pos >= state.queue.len() {
+                    return Err(String::from("Bad song index"));
+                }
+                if state.current != Some(pos) || state.state == PlaybackState::Stop {
+                    state.elapsed = 0.0;
+                }
+                state.current = Some(pos);
+                state.state = PlaybackState::Play;
+            },
+            "pause" => {
+                state.state = match (argument(1), state.state) {
+                    ("1", _) | ("", PlaybackState::Play) => PlaybackState::Pause,
+                    (_, PlaybackState::Stop) => PlaybackState::Stop,
+                    _ => PlaybackState::Play,
+                };
+            },
+            "stop" => {
+                state.state = PlaybackState::Stop;
+                state.elapsed = 0.0;
+            },
+            "next" | "previous" => {
+                if let Some(pos) = state.current {
+                    let new_pos = if command == "next" { pos + 1 } else { pos.saturating_sub(1) };
+                    state.elapsed = 0.0;
+                    if new_pos < state.queue.len() {
+                        state.current = Some(new_pos);
+                    }
+                    else {
+                        state.current = None;
+                        state.state = PlaybackState::Stop;
+                    }
+                }
+            },
+            "seekcur" => {
+                if state.state == PlaybackState::Stop {
+                    return Err(String::from("Not playing"));
+                }
+                let seconds = argument(1).parse::<f64>().map_err(|_| String::from("Number expected"))?;
+                state.elapsed = seconds.max(0.0);
+            },
+            "setvol" => {
+                let volume = argument(1).parse::<i8>().map_err(|_| String::from("Number expected"))?;
+                state.volume = volume.clamp(0, 100);
+            },
+            "repeat" => state.repeat = argument(1) == "1",
+            "random" => state.random = argument(1) == "1",
+            "single" => state.single = argument(1) == "1",
+            "consume" => state.consume = argument(1) == "1",
+            "clear" => {
+                state.queue.clear();
+                state.current = None;
+                state.state = PlaybackState::Stop;
+                state.queue_version += 1;
+            },
+            "add" | "addid" => {
+                // urls are added as they are, files have to be in the library
+                let track = match state.library.iter().find(|t| t.file == argument(1)) {
+                    Some(t) => t.clone(),
+                    None if argument(1).contains("://") => Track { file: argument(1).to_string(), ..Track::default() },
+                    None => return Err(String::from("No such directory")),
+                };
+                state.queue.push(track);
+                state.queue_version += 1;
+                if command == "addid" {
+                    response.push_str(&format!("Id: {}\n", state.queue.len()));
+                }
+            },
+            "delete" => {
+                let pos = argument(1).parse::<usize>().map_err(|_| String::from("Number expected"))?;
+                if pos >= state.queue.len() {
+                    return Err(String::from("Bad song index"));
+                }
+                state.queue.remove(pos);
+                state.queue_version += 1;
+                state.current = match state.current {
+                    Some(c) if c == pos => None,
+                    Some(c) if c > pos => Some(c - 1),
+                    c => c,
+                };
+            },
+            "playlistinfo" => {
+                for (pos, track) in state.queue.iter().enumerate() {
+                    response.push_str(&song_response(track, Some(pos)));
+                }
+            },
+            "listplaylists" => {
+                for (name, _) in &state.playlists {
+                    response.push_str(&format!("playlist: {}\nLast-Modified: 2020-01-01T00:00:00Z\n", name));
+                }
+            },
+            "listplaylistinfo" | "listplaylist" => {
+                let tracks = find_playlist(&state, argument(1))?;
+                for track in tracks {
+                    if command == "listplaylist" {
+                        response.push_str(&format!("file: {}\n", track.file));
+                    }
+                    else {
+                        response.push_str(&song_response(&track, None));
+                    }
+                }
+            },
+            "load" => {
+                let tracks = find_playlist(&state, argument(1))?;
+                let (start, end) = parse_range(argument(2), tracks.len());
+                state.queue.extend_from_slice(&tracks[start..end]);
+                state.queue_version += 1;
+            },
+            "save" => {
+                if state.playlists.iter().any(|(name, _)| name == argument(1)) {
+                    return Err(String::from("Playlist already exists"));
+                }
+                let queue = state.queue.clone();
+                state.playlists.push((argument(1).to_string(), queue));
+            },
+            "playlistadd" => {
+                let track = match state.library.iter().find(|t| t.file == argument(2)) {
+                    Some(t) => t.clone(),
+                    None => Track { file: argument(2).to_string(), ..Track::default() },
+                };
+                match state.playlists.iter_mut().find(|(name, _)| name == argument(1)) {
+                    Some((_, tracks)) => tracks.push(track),
+                    None => state.playlists.push((argument(1).to_string(), vec![track])),
+                }
+            },
+            "playlistdelete" | "playlistmove" => {
+                let tracks = match state.playlists.iter_mut().find(|(name, _)| name == argument(1)) {
+                    Some((_, tracks)) => tracks,
+                    None => return Err(String::from("No such playlist")),
+                };
+                let pos = argument(2).parse::<usize>().map_err(|_| String::from("Number expected"))?;
+                if pos >= tracks.len() {
+                    return Err(String::from("Bad song index"));
+                }
+                let track = tracks.remove(pos);
+                if command == "playlistmove" {
+                    let to = argument(3).parse::<usize>().map_err(|_| String::from("Number expected"))?;
+                    tracks.insert(to.min(tracks.len()), track);
+                }
+            },
+            "rm" => {
+                find_playlist(&state, argument(1))?;
+                state.playlists.retain(|(name, _)| name != argument(1));
+            },
+            "find" | "search" => {
+                // the arguments are pairs of tag and value, "any" matches every tag
+                // the tracks have no modification time, so they match every modified-since
+                let filters: Vec<&[String]> = arguments[1..].chunks(2).filter(|pair| pair[0] != "window").collect();
+                if filters.is_empty() {
+                    return Err(String::from("too few arguments"));
+                }
+                let window = arguments[1..].chunks(2).find(|pair| pair[0] == "window").and_then(|pair| pair.get(1));
+                let (start, end) = match window {
+                    Some(range) => parse_range(range, state.library.len()),
+                    None => (0, state.library.len()),
+                };
+
+                let mut found = 0;
+                for track in &state.library {
+                    let matches = filters.iter().all(|pair| {
+                        let value = match pair.get(1) {
+                            Some(v) => v.to_lowercase(),
+                            None => return true,
+                        };
+                        if pair[0] == "modified-since" {
+                            return true;
+                        }
+                        track_tag_values(track, &pair[0]).iter().any(|v| {
+                            if command == "find" { v.to_lowercase() == value } else { v.to_lowercase().contains(&value) }
+                        })
+                    });
+                    if matches {
+                        if found >= start && found < end {
+                            response.push_str(&song_response(track, None));
+                        }
+                        found += 1;
+                    }
+                }
+            },
+            "list" => {
+                let mut values: Vec<String> = Vec::new();
+                for track in &state.library {
+                    for value in track_tag_values(track, argument(1)) {
+                        if !values.contains(&value) {
+                            values.push(value);
+                        }
+                    }
+                }
+                let key = capitalize_tag(argument(1));
+                for value in values {
+                    response.push_str(&format!("{}: {}\n", key, value));
+                }
+            },
+            "outputs" => {
+                response.push_str("outputid: 0\noutputname: fake output\nplugin: null\noutputenabled: 1\n");
+            },
+            "tagtypes" => {
+                response.push_str("tagtype: Artist\ntagtype: AlbumArtist\ntagtype: Album\ntagtype: Title\n");
+            },
+            _ => return Err(format!("unknown command \"{}\"", command)),
+        }
+
+        if let Some(subsystem) = changed_subsystem(command) {
+            state.events.push(subsystem.to_string());
+            self.changed.notify_all();
+        }
+
+        Ok(response)
+    }
+}
+
+// formats an error response
+fn ack(list_index: usize, line: &str, message: &str) -> String {
+    let command = line.split_whitespace().next().unwrap_or("");
+    format!("ACK [5@{}] {{{}}} {}\n", list_index, command, message)
+}
+
+// the idle subsystem a successful command changes
+fn changed_subsystem(command: &str) -> Option<&'static str> {
+    match command {
+        "play" | "playid" | "pause" | "stop" | "next" | "previous" | "seekcur" => Some("player"),
+        "setvol" => Some("mixer"),
+        "repeat" | "random" | "single" | "consume" => Some("options"),
+        "clear" | "add" | "addid" | "delete" | "load" => Some("playlist"),
+        "save" | "rm" | "playlistadd" | "playlistdelete" | "playlistmove" => Some("stored_playlist"),
+        _ => None,
+    }
+}
+
+fn find_playlist(state: &FakeState, name: &str) -> Result<Vec<Track>, String> {
+    match state.playlists.iter().find(|(n, _)| n == name) {
+        Some((_, tracks)) => Ok(tracks.clone()),
+        None => Err(String::from("No such playlist")),
+    }
+}
+
+// formats a song the way mpd does, with position and id if it is part of the queue
+fn song_response(track: &Track, pos: Option<usize>) -> String {
+    let mut response = format!("file: {}\n", track.file);
+
+    let tags = [("Name", &track.name), ("Title", &track.title), ("Artist", &track.artist), ("AlbumArtist", &track.album_artist), ("Album", &track.album)];
+    for (key, value) in tags.iter() {
+        if let Some(value) = value {
+            response.push_str(&format!("{}: {}\n", key, value));
+        }
+    }
+    for (key, value) in &track.tags {
+        response.push_str(&format!("{}: {}\n", key, value));
+    }
+    if let Some(duration) = track.duration {
+        response.push_str(&format!("Time: {}\nduration: {:.3}\n", duration, duration as f64));
+    }
+    if let Some(pos) = pos {
+        response.push_str(&format!("Pos: {}\nId: {}\n", pos, pos + 1));
+    }
+
+    response
+}
+
+// returns the values of a tag of a track, "any" returns all of them
+fn track_tag_values(track: &Track, tag: &str) -> Vec<String> {
+    let tag = tag.to_lowercase();
+    let mut values: Vec<String> = Vec::new();
+
+    let fields = [("title", &track.title), ("artist", &track.artist), ("albumartist", &track.album_artist), ("album", &track.album)];
+    for (key, value) in fields.iter() {
+        if let Some(value) = value {
+            if tag == *key || tag == "any" {
+                values.push(value.clone());
+            }
+        }
+    }
+    for (key, value) in &track.tags {
+        if key.to_lowercase() == tag || tag == "any" {
+            values.push(value.clone());
+        }
+    }
+    if tag == "file" || tag == "any" {
+        values.push(track.file.clone());
+    }
+
+    values
+}
+
+// "albumartist" -> "AlbumArtist" etc., used as key in list responses
+fn capitalize_tag(tag: &str) -> String {
+    match tag.to_lowercase().as_str() {
+        "albumartist" => String::from("AlbumArtist"),
+        "musicbrainz_trackid" => String::from("MUSICBRAINZ_TRACKID"),
+        lower => {
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        },
+    }
+}
+
+// parses a range argument like "2:5" or "2:" and clamps it to the given length
+fn parse_range(argument: &str, length: usize) -> (usize, usize) {
+    if argument.is_empty() {
+        return (0, length);
+    }
+
+    let mut parts = argument.splitn(2, ':');
+    let start = parts.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(0).min(length);
+    let end = match parts.next() {
+        Some(e) => e.parse::<usize>().unwrap_or(length),
+        None => start + 1,
+    };
+
+    (start, end.min(length).max(start))
+}
+
+// splits a command line into its arguments, handling quotes and backslash escapes
+fn parse_arguments(line: &str) -> Vec<String> {
+    let mut arguments: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_argument = false;
+    let mut chars = line.trim().chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => { in_quotes = !in_quotes; has_argument = true; },
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            },
+            ' ' | '\t' if !in_quotes => {
+                if has_argument {
+                    arguments.push(current.clone());
+                    current.clear();
+                    has_argument = false;
+                }
+            },
+            _ => { current.push(c); has_argument = true; },
+        }
+    }
+
+    if has_argument {
+        arguments.push(current);
+    }
+
+    arguments
+}
+
+// a new server with the demo playlists and a player connected to it over tcp
+#[cfg(test)]
+pub fn connect_fake() -> (FakeMpd, Player) {
+    let server = FakeMpd::new(demo_playlists());
+    let address = server.listen_tcp("127.0.0.1:0").unwrap().to_string();
+    let player = Player::connect(&address).unwrap();
+    (server, player)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+    use mpd::idle::Subsystem;
+
+    #[test]
+    fn idle_reports_the_changes_of_other_connections() {
+        let (server, mut player) = connect_fake();
+        let address = server.listen_tcp("127.0.0.1:0").unwrap().to_string();
+        let mut waiting = Player::connect(&address).unwrap();
+
+        let changes = thread::spawn(move || waiting.wait_for_changes().unwrap());
+        player.load_playlist(&String::from("Evening"), 0, 3).unwrap();
+        player.play().unwrap();
+
+        // the waiting connection sees the queue change, the playback may be reported by a later idle
+        let changes = changes.join().unwrap();
+        assert_eq!(changes.first(), Some(&Subsystem::Queue));
+        assert!(server.received_commands().iter().any(|command| command == "idle"));
+    }
+
+    #[test]
+    fn idle_returns_events_that_happened_before() {
+        let (_server, mut player) = connect_fake();
+
+        player.set_volume(40).unwrap();
+        player.set_random(true).unwrap();
+        assert_eq!(player.wait_for_changes().unwrap(), vec![Subsystem::Mixer, Subsystem::Options]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serves_unix_sockets() {
+        use std::os::unix::net::UnixStream;
+
+        let server = FakeMpd::new(demo_playlists());
+        let path = std::env::temp_dir().join(format!("fake-mpd-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        server.listen_unix(&path).unwrap();
+
+        let stream = UnixStream::connect(&path).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, format!("OK MPD {}\n", PROTOCOL_VERSION));
+
+        writeln!(writer, "setvol 30").unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "OK\n");
+        assert_eq!(server.state().volume, 30);
+        assert_eq!(server.received_commands(), vec![String::from("setvol 30")]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::keymap::{Action, Keymap};
+use crate::util::app::App;
+use crate::util::stateful_selected_list::CurrentElement;
+use crate::views::View;
+
+// actions of the help grouped by context
+const GROUPS: [(&str, &[Action]); 5] = [
+    ("Navigation", &[
+        Action::Up, Action::Down, Action::Left, Action::Right, Action::PageUp, Action::PageDown,
+        Action::First, Action::Last, Action::FocusNext, Action::FocusPrevious, Action::Select,
+    ]),
+    ("Playback", &[
+        Action::TogglePlay, Action::Stop, Action::NextSong, Action::PreviousSong, Action::SeekForward,
+        Action::SeekBackward, Action::VolumeUp, Action::VolumeDown, Action::ListenLocally,
+    ]),
+    ("Table", &[
+        Action::Filter, Action::SortColumn, Action::ReverseSort, Action::Mark, Action::MarkUp, Action::MarkDown,
+        Action::VisualMode, Action::ClearMarks, Action::Queue, Action::Remove, Action::MoveUp, Action::MoveDown,
+    ]),
+    ("Views", &[
+        Action::View(0), Action::View(1), Action::View(2), Action::View(3), Action::View(4), Action::View(5), Action::View(6),
+    ]),
+    ("Program", &[
+        Action::Help, Action::CommandLine, Action::ReloadConfig, Action::Quit,
+    ]),
+];
+
+// keys of the prompt, they can not be configured
+const COMMAND_LINE_KEYS: [(&str, &str); 6] = [
+    ("tab", "complete command, playlist or tag value"),
+    ("up down", "browse the history"),
+    ("enter", "run the command"),
+    ("esc", "close the command line"),
+    ("ctrl-u", "clear the input"),
+    ("esc (filter)", "remove the filter"),
+];
+
+// the most relevant actions of a block, shown in the footer
+fn footer_actions(element: &CurrentElement) -> Vec<(Action, &'static str)> {
+    let mut actions = match element {
+        CurrentElement::Views => vec![(Action::Select, "show view"), (Action::FocusNext, "next block")],
+        CurrentElement::Playlists => vec![(Action::Select, "open"), (Action::Right, "tracks")],
+        CurrentElement::MainArea => vec![(Action::Select, "play"), (Action::Mark, "mark"), (Action::Queue, "queue"), (Action::Filter, "filter")],
+        CurrentElement::Playbar => vec![(Action::Select, "press"), (Action::Right, "move")],
+        CurrentElement::Timeline => vec![(Action::SeekForward, "seek forward"), (Action::SeekBackward, "seek backward")],
+        _ => Vec::new(),
+    };
+
+    actions.extend_from_slice(&[
+        (Action::TogglePlay, "play/pause"),
+        (Action::CommandLine, "command"),
+        (Action::Help, "help"),
+        (Action::Quit, "quit"),
+    ]);
+
+    actions
+}
+
+// all bindings of the keymap as lines of the help
+pub fn help_lines(keymap: &Keymap) -> Vec<String> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = GROUPS.iter().map(|(title, actions)| {
+        let rows = actions.iter().map(|action| {
+            let description = match action {
+                Action::View(index) => format!("show {}", View::from_index(*index).title()),
+                _ => action.description().to_string(),
+            };
+            (keymap.keys_for(*action).join(", "), description)
+        }).collect();
+        (title.to_string(), rows)
+    }).collect();
+
+    let commands = keymap.command_bindings();
+    if !commands.is_empty() {
+        sections.push((String::from("Commands"), commands.into_iter().map(|(keys, line)| (keys, format!(":{}", line))).collect()));
+    }
+
+    sections.push((String::from("Command line"), COMMAND_LINE_KEYS.iter().map(|(k, d)| (k.to_string(), d.to_string())).collect()));
+
+    // the keys are aligned in one column
+    let width = sections.iter().flat_map(|(_, rows)| rows.iter().map(|(keys, _)| keys.chars().count())).max().unwrap_or(0);
+
+    let mut lines: Vec<String> = Vec::new();
+    for (title, rows) in sections {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(title);
+        for (keys, description) in rows {
+            let keys = if keys.is_empty() { String::from("-") } else { keys };
+            lines.push(format!("  {:width$}  {}", keys, description, width = width));
+        }
+    }
+
+    lines
+}
+
+// the keys of the focused block, e.g. "enter open  right tracks  ? help"
+pub fn footer(app: &App) -> String {
+    footer_actions(&app.current_element).iter()
+        .filter_map(|(action, label)| app.keymap.keys_for(*action).first().map(|keys| format!("{} {}", keys, label)))
+        .collect::<Vec<String>>()
+        .join("  ")
+}
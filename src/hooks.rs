@@ -0,0 +1,202 @@
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::backend::{Track, Status, PlaybackState};
+
+// playback that stops this close to the end of the last song is the end of the queue
+// the elapsed time is from the previous update, so this covers slow tick rates
+const QUEUE_END_MARGIN: i64 = 5;
+
+// events that user commands can be attached to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayerEvent {
+    SongChange,
+    Play,
+    Pause,
+    Stop,
+    QueueEnd,
+    ConnectionLoss,
+}
+
+impl PlayerEvent {
+    // passed to the commands as CMP_EVENT
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerEvent::SongChange => "song-change",
+            PlayerEvent::Play => "play",
+            PlayerEvent::Pause => "pause",
+            PlayerEvent::Stop => "stop",
+            PlayerEvent::QueueEnd => "queue-end",
+            PlayerEvent::ConnectionLoss => "connection-loss",
+        }
+    }
+}
+
+// runs shell commands when the state of the player changes
+// the metadata of the current song is passed in CMP_* environment variables
+pub struct EventHooks {
+    commands: Vec<(PlayerEvent, String)>,
+    last_state: Option<PlaybackState>,
+    last_song: Option<Track>,
+    last_queue_position: Option<u32>,
+    last_queue_length: u32,
+    last_elapsed: i64,
+}
+
+impl EventHooks {
+    pub fn new(commands: Vec<(PlayerEvent, String)>) -> EventHooks {
+        EventHooks {
+            commands,
+            last_state: None,
+            last_song: None,
+            last_queue_position: None,
+            last_queue_length: 0,
+            last_elapsed: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // compares the state with the previous update and runs the commands of all events
+    pub fn update(&mut self, status: &Status, song: Option<Track>) {
+        for event in self.detect_events(status, song) {
+            self.run(event);
+        }
+    }
+
+    // the events between the previous update and this one
+    fn detect_events(&mut self, status: &Status, song: Option<Track>) -> Vec<PlayerEvent> {
+        let mut events: Vec<PlayerEvent> = Vec::new();
+
+        // nothing is reported for the state found on startup
+        if let Some(last_state) = &self.last_state {
+            if song != self.last_song && song.is_some() {
+                events.push(PlayerEvent::SongChange);
+            }
+
+            if status.state != *last_state {
+                events.push(match status.state {
+                    PlaybackState::Play => PlayerEvent::Play,
+                    PlaybackState::Pause => PlayerEvent::Pause,
+                    PlaybackState::Stop => PlayerEvent::Stop,
+                });
+
+                // playback stops by itself at the end of the last song of the queue, a stop
+                // command in the middle of it is no queue end
+                // streams have no duration, the server forgets the current song after them
+                let was_last = match self.last_queue_position {
+                    Some(position) => position + 1 >= self.last_queue_length,
+                    None => false,
+                };
+                let ended = match self.last_song.as_ref().and_then(|s| s.duration) {
+                    Some(duration) => self.last_elapsed + QUEUE_END_MARGIN >= duration as i64,
+                    None => status.queue_position.is_none(),
+                };
+                if status.state == PlaybackState::Stop && *last_state == PlaybackState::Play && was_last && ended {
+                    events.push(PlayerEvent::QueueEnd);
+                }
+            }
+        }
+
+        self.last_state = Some(status.state);
+        self.last_queue_position = status.queue_position;
+        self.last_queue_length = status.queue_length;
+        self.last_elapsed = status.elapsed;
+
+        // the last song is kept while stopped, so the stop commands know what was played
+        if song.is_some() {
+            self.last_song = song;
+        }
+
+        events
+    }
+
+    // runs the commands of an event in the background, their output is discarded
+    pub fn run(&self, event: PlayerEvent) {
+        let song = self.last_song.clone().unwrap_or_default();
+
+        for (_, command) in self.commands.iter().filter(|(e, _)| *e == event) {
+            let child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("CMP_EVENT", event.name())
+                .env("CMP_TITLE", song.title.clone().unwrap_or_default())
+                .env("CMP_ARTIST", song.artist.clone().unwrap_or_default())
+                .env("CMP_ALBUM", song.album.clone().unwrap_or_default())
+                .env("CMP_FILE", song.file.clone())
+                .env("CMP_DURATION", song.duration.map(|d| d.to_string()).unwrap_or_default())
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+
+            // wait in the background so no zombie processes are left behind
+            if let Ok(mut child) = child {
+                thread::spawn(move || child.wait());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(state: PlaybackState, elapsed: i64, queue_position: Option<u32>) -> Status {
+        Status {
+            state,
+            volume: 100,
+            elapsed,
+            queue_position,
+            queue_length: 2,
+            repeat: false,
+            random: false,
+            single: false,
+            consume: false,
+        }
+    }
+
+    fn song(duration: Option<u64>) -> Option<Track> {
+        Some(Track { file: String::from("song.mp3"), duration, ..Track::default() })
+    }
+
+    #[test]
+    fn playback_changes_are_detected() {
+        let mut hooks = EventHooks::new(Vec::new());
+
+        // the state found on startup is no event
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, Some(0)), song(Some(200))), vec![]);
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Play, 0, Some(0)), song(Some(200))), vec![PlayerEvent::Play]);
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Pause, 10, Some(0)), song(Some(200))), vec![PlayerEvent::Pause]);
+
+        let next = Some(Track { file: String::from("next.mp3"), ..Track::default() });
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Play, 0, Some(1)), next), vec![PlayerEvent::SongChange, PlayerEvent::Play]);
+    }
+
+    #[test]
+    fn only_the_end_of_the_last_song_ends_the_queue() {
+        // stopped in the middle of the last song
+        let mut hooks = EventHooks::new(Vec::new());
+        hooks.detect_events(&status(PlaybackState::Play, 60, Some(1)), song(Some(200)));
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, Some(1)), None), vec![PlayerEvent::Stop]);
+
+        // stopped at the end of a song that is not the last one
+        let mut hooks = EventHooks::new(Vec::new());
+        hooks.detect_events(&status(PlaybackState::Play, 199, Some(0)), song(Some(200)));
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, Some(0)), None), vec![PlayerEvent::Stop]);
+
+        // the last song played to its end
+        let mut hooks = EventHooks::new(Vec::new());
+        hooks.detect_events(&status(PlaybackState::Play, 198, Some(1)), song(Some(200)));
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, None), None), vec![PlayerEvent::Stop, PlayerEvent::QueueEnd]);
+
+        // a stream has no end, the server forgets it after the queue ends
+        let mut hooks = EventHooks::new(Vec::new());
+        hooks.detect_events(&status(PlaybackState::Play, 600, Some(1)), song(None));
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, Some(1)), None), vec![PlayerEvent::Stop]);
+        hooks.detect_events(&status(PlaybackState::Play, 600, Some(1)), song(None));
+        assert_eq!(hooks.detect_events(&status(PlaybackState::Stop, 0, None), None), vec![PlayerEvent::Stop, PlayerEvent::QueueEnd]);
+    }
+}
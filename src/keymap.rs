@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// names of the presets that can be selected in the config
+pub const PRESET_NAMES: [&str; 3] = ["default", "vim", "emacs"];
+
+// everything a key can be bound to
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    // navigation
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    FocusNext,
+    FocusPrevious,
+    Select,
+
+    // playback
+    TogglePlay,
+    Stop,
+    NextSong,
+    PreviousSong,
+    SeekForward,
+    SeekBackward,
+    VolumeUp,
+    VolumeDown,
+    ListenLocally,
+
+    // table
+    Filter, // opens the '/' prompt
+    SortColumn, // sorts by the next column
+    ReverseSort,
+    Mark, // marks or unmarks the selected row
+    MarkUp, // marks the selected row and the one above
+    MarkDown,
+    VisualMode, // marks all rows the selection moves over
+    ClearMarks,
+    Queue, // adds the marked rows to the queue
+    Remove, // removes the marked rows from the playlist
+    MoveUp, // moves the marked rows in the playlist
+    MoveDown,
+
+    // views, index into views::VIEWS
+    View(usize),
+
+    // program
+    Help, // shows all bindings
+    CommandLine, // opens the ':' prompt
+    Command(usize), // runs a command of the config, index into Keymap::commands
+    ReloadConfig,
+    Quit,
+}
+
+// all actions with their names in the config, in the order they are documented
+pub const ACTIONS: [(Action, &str); 43] = [
+    (Action::Up, "up"),
+    (Action::Down, "down"),
+    (Action::Left, "left"),
+    (Action::Right, "right"),
+    (Action::PageUp, "page_up"),
+    (Action::PageDown, "page_down"),
+    (Action::First, "first"),
+    (Action::Last, "last"),
+    (Action::FocusNext, "focus_next"),
+    (Action::FocusPrevious, "focus_previous"),
+    (Action::Select, "select"),
+    (Action::TogglePlay, "toggle_play"),
+    (Action::Stop, "stop"),
+    (Action::NextSong, "next_song"),
+    (Action::PreviousSong, "previous_song"),
+    (Action::SeekForward, "seek_forward"),
+    (Action::SeekBackward, "seek_backward"),
+    (Action::VolumeUp, "volume_up"),
+    (Action::VolumeDown, "volume_down"),
+    (Action::ListenLocally, "listen_locally"),
+    (Action::Filter, "filter"),
+    (Action::SortColumn, "sort_column"),
+    (Action::ReverseSort, "reverse_sort"),
+    (Action::Mark, "mark"),
+    (Action::MarkUp, "mark_up"),
+    (Action::MarkDown, "mark_down"),
+    (Action::VisualMode, "visual_mode"),
+    (Action::ClearMarks, "clear_marks"),
+    (Action::Queue, "queue"),
+    (Action::Remove, "remove"),
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::View(0), "view_playlists"),
+    (Action::View(1), "view_queue"),
+    (Action::View(2), "view_library"),
+    (Action::View(3), "view_browse"),
+    (Action::View(4), "view_search"),
+    (Action::View(5), "view_now_playing"),
+    (Action::View(6), "view_stats"),
+    (Action::Help, "help"),
+    (Action::CommandLine, "command_line"),
+    (Action::ReloadConfig, "reload_config"),
+    (Action::Quit, "quit"),
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|(a, _)| a == self).map(|(_, name)| *name).unwrap_or("command")
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, n)| *n == name).map(|(action, _)| *action)
+    }
+
+    // shown in the help
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::Left => "move left",
+            Action::Right => "move right",
+            Action::PageUp => "one page up",
+            Action::PageDown => "one page down",
+            Action::First => "go to the first row",
+            Action::Last => "go to the last row",
+            Action::FocusNext => "focus the next block",
+            Action::FocusPrevious => "focus the previous block",
+            Action::Select => "open playlist, play track or press button",
+            Action::TogglePlay => "play or pause",
+            Action::Stop => "stop playback",
+            Action::NextSong => "next track",
+            Action::PreviousSong => "previous track",
+            Action::SeekForward => "seek forward",
+            Action::SeekBackward => "seek backward",
+            Action::VolumeUp => "volume up",
+            Action::VolumeDown => "volume down",
+            Action::ListenLocally => "listen to the stream on this computer",
+            Action::Filter => "filter the shown tracks",
+            Action::SortColumn => "sort by the next column",
+            Action::ReverseSort => "reverse the sort order",
+            Action::Mark => "mark or unmark the row",
+            Action::MarkUp => "mark the rows up",
+            Action::MarkDown => "mark the rows down",
+            Action::VisualMode => "mark the rows the selection moves over",
+            Action::ClearMarks => "unmark all rows",
+            Action::Queue => "add the marked rows to the queue",
+            Action::Remove => "remove the marked rows from the playlist",
+            Action::MoveUp => "move the marked rows up in the playlist",
+            Action::MoveDown => "move the marked rows down in the playlist",
+            Action::View(_) => "show a view",
+            Action::Help => "show this help",
+            Action::CommandLine => "open the command line",
+            Action::Command(_) => "run a command",
+            Action::ReloadConfig => "reload the config file",
+            Action::Quit => "quit",
+        }
+    }
+}
+
+// maps key sequences to actions
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyEvent>, Action)>,
+    commands: Vec<String>, // command lines bound with Action::Command
+}
+
+impl Keymap {
+    // a preset with the bindings written as in the config
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let bindings: Vec<(&str, Action)> = match name {
+            "default" => vec![
+                ("up", Action::Up), ("down", Action::Down), ("left", Action::Left), ("right", Action::Right),
+                ("pageup", Action::PageUp), ("pagedown", Action::PageDown), ("home", Action::First), ("end", Action::Last),
+                ("tab", Action::FocusNext), ("backtab", Action::FocusPrevious), ("enter", Action::Select),
+                ("space", Action::TogglePlay), ("s", Action::Stop), (">", Action::NextSong), ("<", Action::PreviousSong),
+                (".", Action::SeekForward), (",", Action::SeekBackward), ("+", Action::VolumeUp), ("-", Action::VolumeDown),
+                ("l", Action::ListenLocally), ("/", Action::Filter), ("o", Action::SortColumn), ("O", Action::ReverseSort),
+                ("m", Action::Mark), ("shift-up", Action::MarkUp), ("shift-down", Action::MarkDown), ("v", Action::VisualMode),
+                ("esc", Action::ClearMarks), ("a", Action::Queue), ("delete", Action::Remove),
+                ("ctrl-up", Action::MoveUp), ("ctrl-down", Action::MoveDown),
+                // the digits are counts in all presets
+                ("alt-1", Action::View(0)), ("alt-2", Action::View(1)), ("alt-3", Action::View(2)), ("alt-4", Action::View(3)),
+                ("alt-5", Action::View(4)), ("alt-6", Action::View(5)), ("alt-7", Action::View(6)),
+                ("?", Action::Help), ("f1", Action::Help), (":", Action::CommandLine), ("ctrl-r", Action::ReloadConfig), ("q", Action::Quit),
+            ],
+            "vim" => vec![
+                ("k", Action::Up), ("j", Action::Down), ("h", Action::Left), ("l", Action::Right),
+                ("up", Action::Up), ("down", Action::Down), ("left", Action::Left), ("right", Action::Right),
+                ("ctrl-b", Action::PageUp), ("ctrl-f", Action::PageDown), ("ctrl-u", Action::PageUp), ("ctrl-d", Action::PageDown),
+                ("g g", Action::First), ("G", Action::Last), ("tab", Action::FocusNext), ("backtab", Action::FocusPrevious),
+                ("enter", Action::Select), ("space", Action::TogglePlay), ("s", Action::Stop),
+                (">", Action::NextSong), ("<", Action::PreviousSong), (".", Action::SeekForward), (",", Action::SeekBackward),
+                ("+", Action::VolumeUp), ("-", Action::VolumeDown), ("L", Action::ListenLocally),
+                ("/", Action::Filter), ("o", Action::SortColumn), ("O", Action::ReverseSort),
+                ("m", Action::Mark), ("shift-up", Action::MarkUp), ("shift-down", Action::MarkDown), ("V", Action::VisualMode),
+                ("v", Action::VisualMode), ("esc", Action::ClearMarks), ("a", Action::Queue), ("d d", Action::Remove),
+                ("K", Action::MoveUp), ("J", Action::MoveDown),
+                // the digits are counts in this preset
+                ("alt-1", Action::View(0)), ("alt-2", Action::View(1)), ("alt-3", Action::View(2)), ("alt-4", Action::View(3)),
+                ("alt-5", Action::View(4)), ("alt-6", Action::View(5)), ("alt-7", Action::View(6)),
+                ("?", Action::Help), ("f1", Action::Help), (":", Action::CommandLine), ("ctrl-r", Action::ReloadConfig), ("q", Action::Quit),
+            ],
+            "emacs" => vec![
+                ("ctrl-p", Action::Up), ("ctrl-n", Action::Down), ("ctrl-b", Action::Left), ("ctrl-f", Action::Right),
+                ("up", Action::Up), ("down", Action::Down), ("left", Action::Left), ("right", Action::Right),
+                ("alt-v", Action::PageUp), ("ctrl-v", Action::PageDown), ("alt-<", Action::First), ("alt->", Action::Last),
+                ("tab", Action::FocusNext), ("backtab", Action::FocusPrevious), ("enter", Action::Select),
+                ("space", Action::TogglePlay), ("ctrl-c s", Action::Stop), ("ctrl-c n", Action::NextSong), ("ctrl-c p", Action::PreviousSong),
+                ("alt-f", Action::SeekForward), ("alt-b", Action::SeekBackward), ("+", Action::VolumeUp), ("-", Action::VolumeDown),
+                ("ctrl-c l", Action::ListenLocally), ("ctrl-s", Action::Filter), ("alt-s", Action::SortColumn), ("alt-r", Action::ReverseSort),
+                ("ctrl-c m", Action::Mark), ("shift-up", Action::MarkUp), ("shift-down", Action::MarkDown), ("ctrl-c v", Action::VisualMode),
+                ("ctrl-g", Action::ClearMarks), ("ctrl-c a", Action::Queue), ("ctrl-c d", Action::Remove),
+                ("alt-p", Action::MoveUp), ("alt-n", Action::MoveDown),
+                ("alt-1", Action::View(0)), ("alt-2", Action::View(1)), ("alt-3", Action::View(2)), ("alt-4", Action::View(3)),
+                ("alt-5", Action::View(4)), ("alt-6", Action::View(5)), ("alt-7", Action::View(6)),
+                ("?", Action::Help), ("f1", Action::Help), ("alt-x", Action::CommandLine),
+                ("ctrl-x r", Action::ReloadConfig), ("ctrl-x ctrl-c", Action::Quit),
+            ],
+            _ => return None,
+        };
+
+        Some(Keymap {
+            bindings: bindings.iter().map(|(keys, action)| (parse_sequence(keys).unwrap(), *action)).collect(),
+            commands: Vec::new(),
+        })
+    }
+
+    // replaces the keys of the actions with the configured ones
+    // keys that are taken from another action of the preset are removed there
+    pub fn rebind(&mut self, bindings: &[(Action, Vec<Vec<KeyEvent>>)]) {
+        for (action, sequences) in bindings.iter() {
+            self.bindings.retain(|(keys, a)| a != action && !sequences.contains(keys));
+        }
+        for (action, sequences) in bindings.iter() {
+            for keys in sequences.iter() {
+                self.bindings.push((keys.clone(), *action));
+            }
+        }
+    }
+
+    // binds keys to a command line like "seek +30", replacing a binding of the preset
+    pub fn bind_command(&mut self, keys: Vec<KeyEvent>, command: String) {
+        self.bindings.retain(|(k, a)| *k != keys || matches!(a, Action::Command(_)));
+
+        self.commands.push(command);
+        self.bindings.push((keys, Action::Command(self.commands.len() - 1)));
+    }
+
+    pub fn command(&self, index: usize) -> Option<&String> {
+        self.commands.get(index)
+    }
+
+    // the keys and command lines of all bound commands
+    pub fn command_bindings(&self) -> Vec<(String, String)> {
+        self.bindings.iter().filter_map(|(keys, action)| match action {
+            Action::Command(index) => Some((format_sequence(keys), self.commands[*index].clone())),
+            _ => None,
+        }).collect()
+    }
+
+    // sequences that are bound twice or that can never be completed
+    // because a shorter sequence matches first
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts: Vec<String> = Vec::new();
+
+        for (i, (keys, action)) in self.bindings.iter().enumerate() {
+            for (other_keys, other_action) in self.bindings.iter().skip(i + 1) {
+                if keys == other_keys && action != other_action {
+                    conflicts.push(format!("\"{}\" is bound to {} and {}", format_sequence(keys), action.name(), other_action.name()));
+                }
+                else if keys.len() != other_keys.len() && (keys.starts_with(other_keys) || other_keys.starts_with(keys)) {
+                    let (short, long, short_action) = if keys.len() < other_keys.len() { (keys, other_keys, action) } else { (other_keys, keys, other_action) };
+                    conflicts.push(format!("\"{}\" ({}) hides \"{}\"", format_sequence(short), short_action.name(), format_sequence(long)));
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    // all key sequences of an action, e.g. for the help
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings.iter().filter(|(_, a)| *a == action).map(|(keys, _)| format_sequence(keys)).collect()
+    }
+
+    fn lookup(&self, keys: &[KeyEvent]) -> Lookup {
+        let mut result = Lookup::Unbound;
+
+        for (sequence, action) in self.bindings.iter() {
+            if sequence.as_slice() == keys {
+                return Lookup::Action(*action);
+            }
+            if sequence.starts_with(keys) {
+                result = Lookup::Prefix;
+            }
+        }
+
+        result
+    }
+}
+
+enum Lookup {
+    Action(Action),
+    Prefix, // more keys are needed
+    Unbound,
+}
+
+// result of a key press
+#[derive(PartialEq, Debug)]
+pub enum KeyResult {
+    Action(Action, u32), // the action and how often it should be repeated
+    Pending, // part of a sequence or count
+    Unbound,
+}
+
+// collects the keys of a sequence and the count typed before it (e.g. "5j")
+#[derive(Default)]
+pub struct KeyInput {
+    pending: Vec<KeyEvent>,
+    count: Option<u32>,
+}
+
+impl KeyInput {
+    pub fn feed(&mut self, keymap: &Keymap, key: KeyEvent) -> KeyResult {
+        let key = normalize(key);
+
+        // escape cancels a sequence or count
+        if key.code == KeyCode::Esc && (!self.pending.is_empty() || self.count.is_some()) {
+            self.reset();
+            return KeyResult::Pending;
+        }
+
+        // digits start a count unless a binding starts with them, a count can not start with 0
+        // once a count is started every digit extends it
+        if let KeyCode::Char(c) = key.code {
+            if self.pending.is_empty() && key.modifiers.is_empty() && c.is_ascii_digit() {
+                let bound = !matches!(keymap.lookup(&[key]), Lookup::Unbound);
+                if self.count.is_some() || (!bound && c != '0') {
+                    let digit = c.to_digit(10).unwrap_or(0);
+                    self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit).min(9999));
+                    return KeyResult::Pending;
+                }
+            }
+        }
+
+        self.pending.push(key);
+
+        match keymap.lookup(&self.pending) {
+            Lookup::Action(action) => {
+                let count = self.count.unwrap_or(1).max(1);
+                self.reset();
+                KeyResult::Action(action, count)
+            },
+            Lookup::Prefix => KeyResult::Pending,
+            Lookup::Unbound => {
+                self.reset();
+                KeyResult::Unbound
+            },
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+    }
+}
+
+// shift is part of the character, so it is removed to compare keys
+fn normalize(key: KeyEvent) -> KeyEvent {
+    match key.code {
+        KeyCode::Char(_) | KeyCode::BackTab => KeyEvent { code: key.code, modifiers: key.modifiers - KeyModifiers::SHIFT },
+        _ => key,
+    }
+}
+
+// parses a sequence of keys separated by spaces, e.g. "g g" or "ctrl-x ctrl-c"
+pub fn parse_sequence(value: &str) -> Option<Vec<KeyEvent>> {
+    let keys: Option<Vec<KeyEvent>> = value.split_whitespace().map(parse_key).collect();
+    keys.filter(|k| !k.is_empty())
+}
+
+// parses "q", "R", "enter", "f5", "ctrl-r", "alt-enter", "shift-down", ...
+pub fn parse_key(value: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::empty();
+    let mut rest = value.trim();
+
+    loop {
+        let lower = rest.to_lowercase();
+        if lower.starts_with("ctrl-") && rest.len() > 5 {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = &rest[5..];
+        }
+        else if lower.starts_with("alt-") && rest.len() > 4 {
+            modifiers |= KeyModifiers::ALT;
+            rest = &rest[4..];
+        }
+        else if lower.starts_with("shift-") && rest.len() > 6 {
+            modifiers |= KeyModifiers::SHIFT;
+            rest = &rest[6..];
+        }
+        else {
+            break;
+        }
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name => {
+                let number = name.strip_prefix('f')?.parse::<u8>().ok()?;
+                if number == 0 || number > 12 {
+                    return None;
+                }
+                KeyCode::F(number)
+            },
+        },
+    };
+
+    // characters are matched without shift, see normalize
+    if let KeyCode::Char(c) = code {
+        if modifiers.contains(KeyModifiers::SHIFT) {
+            return Some(KeyEvent { code: KeyCode::Char(c.to_ascii_uppercase()), modifiers: modifiers - KeyModifiers::SHIFT });
+        }
+    }
+
+    Some(KeyEvent { code, modifiers })
+}
+
+// the inverse of parse_key
+pub fn format_key(key: &KeyEvent) -> String {
+    let mut result = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        result.push_str("ctrl-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        result.push_str("alt-");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        result.push_str("shift-");
+    }
+
+    let names: HashMap<KeyCode, &str> = [
+        (KeyCode::Enter, "enter"), (KeyCode::Esc, "esc"), (KeyCode::Tab, "tab"), (KeyCode::BackTab, "backtab"),
+        (KeyCode::Backspace, "backspace"), (KeyCode::Delete, "delete"), (KeyCode::Insert, "insert"), (KeyCode::Char(' '), "space"),
+        (KeyCode::Up, "up"), (KeyCode::Down, "down"), (KeyCode::Left, "left"), (KeyCode::Right, "right"),
+        (KeyCode::Home, "home"), (KeyCode::End, "end"), (KeyCode::PageUp, "pageup"), (KeyCode::PageDown, "pagedown"),
+    ].iter().cloned().collect();
+
+    match (names.get(&key.code), key.code) {
+        (Some(name), _) => result.push_str(name),
+        (None, KeyCode::Char(c)) => result.push(c),
+        (None, KeyCode::F(n)) => result.push_str(&format!("f{}", n)),
+        _ => result.push('?'),
+    }
+
+    result
+}
+
+pub fn format_sequence(keys: &[KeyEvent]) -> String {
+    keys.iter().map(format_key).collect::<Vec<String>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(input: &mut KeyInput, keymap: &Keymap, keys: &str) -> KeyResult {
+        let mut result = KeyResult::Unbound;
+        for key in parse_sequence(keys).unwrap() {
+            result = input.feed(keymap, key);
+        }
+        result
+    }
+
+    #[test]
+    fn digits_after_a_count_extend_it() {
+        // "1" is bound, so it only counts after another digit
+        let mut keymap = Keymap::preset("default").unwrap();
+        keymap.bindings.push((parse_sequence("1").unwrap(), Action::View(0)));
+        let mut input = KeyInput::default();
+
+        assert_eq!(feed(&mut input, &keymap, "9 1 down"), KeyResult::Action(Action::Down, 91));
+        assert_eq!(feed(&mut input, &keymap, "1"), KeyResult::Action(Action::View(0), 1));
+        assert_eq!(feed(&mut input, &keymap, "9 5 alt-5"), KeyResult::Action(Action::View(4), 95));
+    }
+}
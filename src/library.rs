@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use lofty::{Accessor, AudioFile, ItemKey, TaggedFileExt};
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::backend::Track;
+use crate::util::paths::cache_dir;
+
+// file extensions whose tags are indexed
+const INDEXED_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "wav"];
+
+// the indexed formats that rodio can decode, opus and m4a files are only listed
+const PLAYABLE_EXTENSIONS: [&str; 5] = ["mp3", "flac", "ogg", "oga", "wav"];
+
+// first line of the cache file, has to be changed when the format changes
+const CACHE_HEADER: &str = "rust-cmp library cache v2";
+
+// tags that are stored in Track::tags besides the dedicated fields
+const EXTRA_TAGS: [&str; 7] = ["Track", "Disc", "Genre", "Date", "Composer", "Rating", "Bitrate"];
+
+// a cached entry for a single file
+#[derive(Clone)]
+struct LibraryEntry {
+    modified: u64, // modification time in seconds since the epoch
+    track: Track,
+}
+
+// index of all music files in a directory tree
+// the index is cached on disk and kept up to date by watching the directory
+pub struct Library {
+    root: PathBuf,
+    entries: BTreeMap<PathBuf, LibraryEntry>, // sorted by path
+    cache_path: Option<PathBuf>,
+    _watcher: Option<RecommendedWatcher>, // has to be kept alive to receive events
+    events: Option<Receiver<DebouncedEvent>>,
+    scan: Option<Receiver<BTreeMap<PathBuf, LibraryEntry>>>, // result of the first scan, set while it runs
+    changed: bool, // set when the index changes, see changed
+}
+
+impl Library {
+    // loads the cached index of the directory, updates it and starts watching the directory
+    pub fn open(root: &Path) -> Library {
+        let mut library = Library {
+            root: root.to_path_buf(),
+            entries: BTreeMap::new(),
+            cache_path: cache_dir().map(|dir| dir.join("library.cache")),
+            _watcher: None,
+            events: None,
+            scan: None,
+            changed: false,
+        };
+
+        library.load_cache();
+
+        // reading the tags of a large directory takes a while, so the first scan runs in the
+        // background and the cached index is used until refresh picks up its result
+        let (scan_tx, scan_rx) = channel();
+        let scan_root = library.root.clone();
+        let cached = library.entries.clone();
+        thread::spawn(move || {
+            let _ = scan_tx.send(scan_entries(&scan_root, cached));
+        });
+        library.scan = Some(scan_rx);
+
+        // watching is optional, without it the index is only updated on startup
+        let (tx, rx) = channel();
+        if let Ok(mut w) = watcher(tx, Duration::from_secs(2)) {
+            if w.watch(&library.root, RecursiveMode::Recursive).is_ok() {
+                library._watcher = Some(w);
+                library.events = Some(rx);
+            }
+        }
+
+        library
+    }
+
+    // scans the whole directory tree
+    // only files that changed since they were indexed are read again
+    pub fn scan(&mut self) {
+        let entries = mem::take(&mut self.entries);
+        self.entries = scan_entries(&self.root, entries);
+    }
+
+    // applies the result of the first scan and all changes reported by the file system watcher
+    pub fn refresh(&mut self) {
+        // the changes are kept in the channel until the scan is done, it would overwrite them
+        if let Some(rx) = &self.scan {
+            match rx.try_recv() {
+                Ok(entries) => {
+                    self.entries = entries;
+                    self.scan = None;
+                    self.changed = true;
+                    self.save_cache();
+                },
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => self.scan = None,
+            }
+        }
+
+        let events: Vec<DebouncedEvent> = match &self.events {
+            Some(rx) => rx.try_iter().collect(),
+            None => return,
+        };
+
+        if events.is_empty() {
+            return;
+        }
+
+        for event in events {
+            match event {
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => self.index_path(&path),
+                DebouncedEvent::Remove(path) => self.remove_path(&path),
+                DebouncedEvent::Rename(from, to) => {
+                    self.remove_path(&from);
+                    self.index_path(&to);
+                },
+                DebouncedEvent::Rescan => self.scan(),
+                _ => {},
+            }
+        }
+
+        self.changed = true;
+        self.save_cache();
+    }
+
+    // returns true if the index changed since the last call
+    pub fn changed(&mut self) -> bool {
+        self.refresh();
+        mem::replace(&mut self.changed, false)
+    }
+
+    // get the indexed track of a file, files outside of the index are read directly
+    pub fn get_track(&self, path: &Path) -> Track {
+        match self.entries.get(path) {
+            Some(entry) => entry.track.clone(),
+            None => read_track(path, &self.root),
+        }
+    }
+
+    // get all indexed tracks in a directory and its sub directories sorted by path
+    pub fn get_tracks_in(&self, dir: &Path) -> Vec<Track> {
+        self.entries.iter()
+            .filter(|(path, _)| path.starts_with(dir))
+            .map(|(_, entry)| entry.track.clone())
+            .collect()
+    }
+
+    // adds a file or all files in a directory to the index
+    fn index_path(&mut self, path: &Path) {
+        if path.is_dir() {
+            let mut files: Vec<PathBuf> = Vec::new();
+            collect_files(path, &mut files);
+            for file in files {
+                index_file(&mut self.entries, &self.root, &file);
+            }
+        }
+        else if is_indexed_file(path) {
+            index_file(&mut self.entries, &self.root, path);
+        }
+    }
+
+    // removes a file or all files in a directory from the index
+    fn remove_path(&mut self, path: &Path) {
+        self.entries.retain(|p, _| !p.starts_with(path));
+    }
+
+    fn load_cache(&mut self) {
+        let content = match &self.cache_path {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(c) => c,
+                Err(_) => return,
+            },
+            None => return,
+        };
+
+        let mut lines = content.lines();
+
+        // the cache is only valid for the same format and music directory
+        if lines.next() != Some(CACHE_HEADER) || lines.next() != self.root.to_str() {
+            return;
+        }
+
+        for line in lines {
+            if let Some((path, entry)) = parse_cache_line(line) {
+                self.entries.insert(path, entry);
+            }
+        }
+    }
+
+    // writes the index to the cache file, errors are ignored as the cache is optional
+    fn save_cache(&self) {
+        let path = match &self.cache_path {
+            Some(p) => p,
+            None => return,
+        };
+        let root = match self.root.to_str() {
+            Some(r) => r,
+            None => return,
+        };
+
+        let mut content = format!("{}\n{}\n", CACHE_HEADER, root);
+        for entry in self.entries.values() {
+            content.push_str(&format_cache_line(entry));
+            content.push('\n');
+        }
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(mut file) = fs::File::create(path) {
+            let _ = file.write_all(content.as_bytes());
+        }
+    }
+}
+
+// updates the index with all files of the directory tree
+// only files that changed since they were indexed are read again
+fn scan_entries(root: &Path, mut entries: BTreeMap<PathBuf, LibraryEntry>) -> BTreeMap<PathBuf, LibraryEntry> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    collect_files(root, &mut files);
+
+    // forget files that do not exist anymore
+    let existing: HashSet<&PathBuf> = files.iter().collect();
+    entries.retain(|path, _| existing.contains(path));
+
+    for file in files.iter() {
+        index_file(&mut entries, root, file);
+    }
+
+    entries
+}
+
+// reads the tags of a file unless the index is up to date
+fn index_file(entries: &mut BTreeMap<PathBuf, LibraryEntry>, root: &Path, path: &Path) {
+    let modified = modification_time(path);
+
+    if let Some(entry) = entries.get(path) {
+        if entry.modified == modified {
+            return;
+        }
+    }
+
+    entries.insert(path.to_path_buf(), LibraryEntry {
+        modified,
+        track: read_track(path, root),
+    });
+}
+
+// reads the tags of a file
+// missing title, artist and album are taken from an "Artist/Album/Track" directory layout
+// below the music directory, the directories above it are not part of the layout
+fn read_track(path: &Path, root: &Path) -> Track {
+    let dirs = path.parent().and_then(|parent| parent.strip_prefix(root).ok()).unwrap_or(Path::new(""));
+    let mut parents = dirs.iter().rev().filter_map(|n| n.to_str());
+    let dir_album = parents.next().map(|a| a.to_string());
+    let dir_artist = parents.next().map(|a| a.to_string());
+
+    let mut track = Track {
+        file: path.to_string_lossy().into_owned(),
+        title: path.file_stem().map(|s| s.to_string_lossy().into_owned()),
+        artist: dir_artist,
+        album: dir_album,
+        ..Track::default()
+    };
+
+    let tagged_file = match lofty::read_from_path(path) {
+        Ok(f) => f,
+        Err(_) => return track,
+    };
+
+    let duration = tagged_file.properties().duration();
+    if duration.as_secs() > 0 {
+        track.duration = Some(duration.as_secs());
+    }
+    if let Some(bitrate) = tagged_file.properties().audio_bitrate() {
+        track.tags.insert(String::from("Bitrate"), bitrate.to_string());
+    }
+
+    let tag = match tagged_file.primary_tag().or(tagged_file.first_tag()) {
+        Some(t) => t,
+        None => return track,
+    };
+
+    if let Some(title) = tag.title() {
+        track.title = Some(title.to_string());
+    }
+    if let Some(artist) = tag.artist() {
+        track.artist = Some(artist.to_string());
+    }
+    if let Some(album) = tag.album() {
+        track.album = Some(album.to_string());
+    }
+    track.album_artist = tag.get_string(&ItemKey::AlbumArtist).map(|a| a.to_string());
+
+    if let Some(number) = tag.track() {
+        track.tags.insert(String::from("Track"), number.to_string());
+    }
+    if let Some(number) = tag.disk() {
+        track.tags.insert(String::from("Disc"), number.to_string());
+    }
+    if let Some(genre) = tag.genre() {
+        track.tags.insert(String::from("Genre"), genre.to_string());
+    }
+    if let Some(date) = tag.get_string(&ItemKey::RecordingDate) {
+        track.tags.insert(String::from("Date"), date.to_string());
+    }
+    else if let Some(year) = tag.year() {
+        track.tags.insert(String::from("Date"), year.to_string());
+    }
+    if let Some(composer) = tag.get_string(&ItemKey::Composer) {
+        track.tags.insert(String::from("Composer"), composer.to_string());
+    }
+    if let Some(rating) = tag.get_string(&ItemKey::Unknown(String::from("RATING"))) {
+        track.tags.insert(String::from("Rating"), rating.to_string());
+    }
+
+    track
+}
+
+pub fn is_indexed_file(path: &Path) -> bool {
+    has_extension(path, &INDEXED_EXTENSIONS)
+}
+
+// true if the file can be played without mpd
+pub fn is_playable_file(path: &Path) -> bool {
+    has_extension(path, &PLAYABLE_EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => extensions.iter().any(|i| i.eq_ignore_ascii_case(ext)),
+        None => false,
+    }
+}
+
+// recursively collects all indexed files of a directory
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        }
+        else if is_indexed_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn modification_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// +-------------------------------------------------------------------+
+// | cache format: one line per file, fields separated by tabs          |
+// | path, mtime, title, artist, album artist, album, duration, extras  |
+// +-------------------------------------------------------------------+
+
+fn format_cache_line(entry: &LibraryEntry) -> String {
+    let track = &entry.track;
+    let optional = |value: &Option<String>| escape(value.as_ref().map(|v| v.as_str()).unwrap_or(""));
+
+    let mut fields: Vec<String> = vec![
+        escape(&track.file),
+        entry.modified.to_string(),
+        optional(&track.title),
+        optional(&track.artist),
+        optional(&track.album_artist),
+        optional(&track.album),
+        track.duration.map(|d| d.to_string()).unwrap_or_default(),
+    ];
+
+    for tag in EXTRA_TAGS.iter() {
+        fields.push(escape(track.tags.get(*tag).map(|v| v.as_str()).unwrap_or("")));
+    }
+
+    fields.join("\t")
+}
+
+fn parse_cache_line(line: &str) -> Option<(PathBuf, LibraryEntry)> {
+    let fields: Vec<String> = line.split('\t').map(unescape).collect();
+    if fields.len() != 7 + EXTRA_TAGS.len() {
+        return None;
+    }
+
+    let optional = |i: usize| if fields[i].is_empty() { None } else { Some(fields[i].clone()) };
+
+    let mut track = Track {
+        file: fields[0].clone(),
+        title: optional(2),
+        artist: optional(3),
+        album_artist: optional(4),
+        album: optional(5),
+        duration: fields[6].parse::<u64>().ok(),
+        ..Track::default()
+    };
+
+    for (i, tag) in EXTRA_TAGS.iter().enumerate() {
+        if let Some(value) = optional(7 + i) {
+            track.tags.insert(tag.to_string(), value);
+        }
+    }
+
+    Some((PathBuf::from(&fields[0]), LibraryEntry {
+        modified: fields[1].parse::<u64>().ok()?,
+        track,
+    }))
+}
+
+// escaping of tabs and newlines for the line based cache files
+pub fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+pub fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {},
+            }
+        }
+        else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the files do not exist, so only the directory layout is used
+    #[test]
+    fn directory_layout_stops_at_the_music_directory() {
+        let root = Path::new("/home/user/Music");
+        let tags = |path: &str| {
+            let track = read_track(Path::new(path), root);
+            (track.artist, track.album)
+        };
+
+        assert_eq!(tags("/home/user/Music/Artist/Album/01 Song.mp3"), (Some(String::from("Artist")), Some(String::from("Album"))));
+        assert_eq!(tags("/home/user/Music/Album/01 Song.mp3"), (None, Some(String::from("Album"))));
+        assert_eq!(tags("/home/user/Music/01 Song.mp3"), (None, None));
+        assert_eq!(read_track(Path::new("/home/user/Music/01 Song.mp3"), root).title, Some(String::from("01 Song")));
+    }
+
+    #[test]
+    fn opus_and_m4a_are_indexed_but_not_playable() {
+        for file in ["song.opus", "song.M4A"].iter() {
+            assert!(is_indexed_file(Path::new(file)));
+            assert!(!is_playable_file(Path::new(file)));
+        }
+        assert!(is_playable_file(Path::new("song.Flac")));
+        assert!(!is_indexed_file(Path::new("cover.jpg")));
+    }
+}
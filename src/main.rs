@@ -2,8 +2,38 @@ mod terminal;
 mod util;
 mod player_interface;
 mod player;
+mod audio_player;
+mod memory_player;
+mod backend;
+mod fake_mpd;
+mod library;
+mod stream_listener;
+mod stations;
+mod mpris;
+mod notifications;
+mod scrobbler;
+mod hooks;
+mod cli;
+mod template;
+mod config;
+mod keymap;
+mod mouse;
+mod command_line;
+mod help;
+mod columns;
+mod sorting;
+mod views;
 
 use player_interface::PlayerInterface;
+use player::Player;
+use audio_player::AudioPlayer;
+use memory_player::MemoryPlayer;
+use fake_mpd::FakeMpd;
+use backend::Backend;
+use config::Config;
+use keymap::{Action, KeyResult};
+use mouse::MouseCommand;
+use command_line::PromptCommand;
 
 use crate::util::{
     app::App,
@@ -12,7 +42,8 @@ use crate::util::{
 };
 
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
+    ExecutableCommand,
+    event::{self, Event as CEvent, DisableMouseCapture, KeyCode, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode},
 };
 use tui::{backend::CrosstermBackend, Terminal};
@@ -23,6 +54,8 @@ use std::{
     sync::mpsc,
     sync::mpsc::Receiver,
     net::IpAddr,
+    net::SocketAddr,
+    io::stdout,
 };
 
 fn main() {
@@ -30,22 +63,26 @@ fn main() {
     // parses command line arguments
     let cli: CmdArgs = argh::from_env();
 
+    // reads the config file, the command line options override its values
+    let config = match Config::load(&cli) {
+        Err(message) => {println!("Error: {}", message); std::process::exit(1)},
+        Ok(c) => c,
+    };
+
     // checks if IP address is valid
     // this also checks the default address (which should be valid)
-    match cli.ip.parse::<IpAddr>() {
+    let server_ip = match config.server.ip.parse::<IpAddr>() {
         Err(_v) => {println!("Error: IP Address not valid!"); std::process::exit(1)},
         Ok(x) => x,
     };
 
-    // checks if the port is valid
-    // this also checks the default port (which should be valid)
-    match cli.port.parse::<i16>() {
-        Err(_v) => {println!("Error: Port not valid!"); std::process::exit(1)},
-        Ok(x) => x,
-    };
-
     // constructs string of ip with port to be used later
-    let ip_with_port : String = format!("{}:{}", cli.ip, cli.port);
+    let ip_with_port : String = SocketAddr::new(server_ip, config.server.port).to_string();
+
+    // subcommands control the server without opening the TUI
+    if let Some(command) = &cli.command {
+        std::process::exit(cli::run(command, &ip_with_port));
+    }
 
     match enable_raw_mode() {
         Ok(_) => {},
@@ -57,13 +94,16 @@ fn main() {
 
     // Setup input handling
     let (tx, rx) = mpsc::channel();
+    let tick_rate = config.ui.tick_rate;
 
     thread::spawn(move || {
         loop {
             // poll for tick rate duration, if no events, sent tick event.
-            if event::poll(Duration::from_millis(cli.tick_rate)).unwrap() {
-                if let CEvent::Key(key) = event::read().unwrap() {
-                    tx.send(Event::Input(key)).unwrap();
+            if event::poll(Duration::from_millis(tick_rate)).unwrap() {
+                match event::read().unwrap() {
+                    CEvent::Key(key) => tx.send(Event::Input(key)).unwrap(),
+                    CEvent::Mouse(mouse) => tx.send(Event::Mouse(mouse)).unwrap(),
+                    _ => {},
                 }
             }
 
@@ -73,11 +113,48 @@ fn main() {
 
     // create app with basic values
     let mut app = App::new();
+    app.apply_config(&config);
 
     // create instance of player interface and initialize playlist view
-    let mut player_interface = PlayerInterface::new(&ip_with_port);
+    // play local files if a music directory is given, otherwise connect to the mpd server
+    let music_player: Box<dyn Backend> = if cli.demo {
+        Box::new(MemoryPlayer::with_demo_library())
+    }
+    else if cli.fake_mpd {
+        // serve the demo library from an in-process fake mpd server and connect to it
+        let fake_server = FakeMpd::new(memory_player::demo_playlists());
+        let address = match fake_server.listen_tcp("127.0.0.1:0") {
+            Ok(a) => a.to_string(),
+            Err(_) => {println!("Error: could not start the fake MPD server!"); std::process::exit(1)},
+        };
+        Box::new(Player::new(&address))
+    }
+    else {
+        match &config.server.music_dir {
+            Some(music_dir) => Box::new(AudioPlayer::new(music_dir)),
+            None => Box::new(Player::new(&ip_with_port)),
+        }
+    };
+    // the httpd output is expected on the server unless a url is given
+    let stream_url = match &config.server.stream_url {
+        Some(url) => url.clone(),
+        None => format!("http://{}/", SocketAddr::new(server_ip, config.server.stream_port)),
+    };
+
+    let mut player_interface = PlayerInterface::new(music_player, stream_url, config.server.stream_url.is_none());
     player_interface.initialize(&mut app);
 
+    // allow media keys and desktop widgets to control the player
+    if config.behaviour.mpris {
+        player_interface.enable_mpris();
+    }
+
+    // notifications, scrobbling and event hooks
+    player_interface.apply_config(&config);
+
+    // reason of an unexpected shutdown, printed after the terminal is cleared
+    let mut exit_message: Option<&str> = None;
+
     // main program loop
     while !app.should_quit {
 
@@ -85,12 +162,45 @@ fn main() {
         match terminal::draw_terminal(&mut terminal, &mut app) {
             Ok(_) => {},
             // exit the program if the terminal could not be drawn
-            Err(_) => {println!("Error: could not draw TUI. Program is shutting down..."); app.should_quit = true; continue;},
+            Err(_) => {
+                quit(&mut app, &mut terminal);
+                exit_message = Some("Error: could not draw TUI. Program is shutting down...");
+                continue;
+            },
         }
 
         handle_user_input(&mut app, &mut terminal, &rx, &mut player_interface); // handle user input
+
+        // settings of the server and the tick rate are only read on startup
+        if app.reload_requested {
+            app.reload_requested = false;
+            match Config::load(&cli) {
+                Ok(config) => {
+                    app.apply_config(&config);
+                    player_interface.apply_config(&config);
+                    player_interface.refresh_table(&mut app);
+                    app.message = String::from("config reloaded");
+                },
+                Err(message) => app.message = message.replace('\n', " "),
+            }
+        }
+
+        // the remaining calls would fail without a connection
+        if !player_interface.check_connection() {
+            quit(&mut app, &mut terminal);
+            exit_message = Some("Error: lost the connection to the server. Program is shutting down...");
+            continue;
+        }
+
+        player_interface.run_event_hooks(); // run the user commands of player events
         terminal::terminal_navigation(&mut app); // handle the terminal navigation
+        // handle requests of MPRIS clients
+        if player_interface.handle_remote_commands(&mut app) {
+            quit(&mut app, &mut terminal);
+            continue;
+        }
         player_interface.update_meta_display(&mut app); // update display of title and artist
+        player_interface.check_library(&mut app); // show the tracks of a finished library scan
     }
 
     // clear the terminal before exiting the program
@@ -102,42 +212,99 @@ fn main() {
     // shut down the interface
     player_interface.quit();
 
+    if let Some(message) = exit_message {
+        println!("{}", message);
+    }
+
 }
 
 // handles the user input for the app
-fn handle_user_input (mut app : &mut App, terminal : &mut Terminal<CrosstermBackend<std::io::Stdout>>, rx : &Receiver<Event<crossterm::event::KeyEvent>>, player_interface : &mut PlayerInterface)
+fn handle_user_input (app : &mut App, terminal : &mut Terminal<CrosstermBackend<std::io::Stdout>>, rx : &Receiver<Event<crossterm::event::KeyEvent>>, player_interface : &mut PlayerInterface)
 {
     match rx.recv() {
-        Ok(Event::Input(event)) => match event.code {
-            // check if q has been pressed to exit the program
-            KeyCode::Char('q') => {
+        Ok(Event::Input(event)) => {
+            // messages are shown until the next key press
+            app.message = String::new();
+
+            // the prompt gets all keys while it is open
+            if app.command_line.active && app.command_line.is_filter() {
+                app.command_line.handle_key(event, player_interface);
+                // the rows are filtered while typing, esc removes the filter
+                let cancelled = event.code == KeyCode::Esc || (event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL));
+                let filter = if cancelled { String::new() } else { app.command_line.input.trim().to_string() };
+                player_interface.set_filter(filter, app);
+                return;
+            }
+            if app.command_line.active {
+                if let Some(line) = app.command_line.handle_key(event, player_interface) {
+                    run_command_line(&line, app, terminal, player_interface);
+                }
+                return;
+            }
 
-                // tells the program to shut down
-                app.should_quit = true;
+            // esc closes the help, the other keys work as configured
+            if app.help_visible && event.code == KeyCode::Esc {
+                app.help_visible = false;
+                return;
+            }
 
-                match disable_raw_mode() {
-                    Ok(_) => {},
-                    Err(_) => {println!("Error: Could not disable raw mode! Program is continuing regardless.")}
-                }
+            let (action, count) = match app.key_input.feed(&app.keymap, event) {
+                KeyResult::Action(action, count) => (action, count),
+                KeyResult::Pending | KeyResult::Unbound => return,
+            };
 
-                match terminal.show_cursor() {
-                    Ok(_) => {},
-                    Err(_) => println!("Error: Cursor could not be shown again! Program is continuing regardless."),
-                } 
+            if app.help_visible {
+                scroll_help(app, action, count);
+                return;
             }
 
-            // check the arrow keys and safe the values to 
-            // state variables in app
-            KeyCode::Left => app.left = true,
-            KeyCode::Up => app.up = true,
-            KeyCode::Right => app.right = true,
-            KeyCode::Down => app.down = true,
+            match action {
+                // check if the quit key has been pressed to exit the program
+                Action::Quit => quit(app, terminal),
+
+                Action::Help => {
+                    app.help_visible = true;
+                    app.help_scroll = 0;
+                },
+
+                Action::CommandLine => app.command_line.open(':', ""),
+                Action::Filter => {
+                    let filter = app.table_order.filter.clone();
+                    app.command_line.open('/', &filter);
+                },
+                Action::Command(index) => {
+                    if let Some(line) = app.keymap.command(index).cloned() {
+                        run_command_line(&line, app, terminal, player_interface);
+                    }
+                },
+
+                // the config is reloaded by the main loop
+                Action::ReloadConfig => app.reload_requested = true,
+
+                // navigation is executed by terminal_navigation, the count repeats it
+                Action::Up | Action::Down | Action::Left | Action::Right |
+                Action::PageUp | Action::PageDown | Action::First | Action::Last |
+                Action::FocusNext | Action::FocusPrevious | Action::Mark | Action::MarkUp | Action::MarkDown |
+                Action::VisualMode | Action::ClearMarks => {
+                    for _ in 0..count {
+                        app.navigation.push_back(action);
+                    }
+                },
+
+                // select and the playback actions
+                _ => player_interface.handle_action(action, count, app),
+            }
+        },
 
-            // check for the Enter key and start the
-            // requested action
-            KeyCode::Enter => player_interface.user_action(&mut app),
+        // clicks focus and select, double clicks and the playbar also start the player
+        Ok(Event::Mouse(event)) => {
+            app.message = String::new();
 
-            _ => {}
+            match mouse::handle_mouse(app, event) {
+                Some(MouseCommand::Select) => player_interface.user_action(app),
+                Some(MouseCommand::Seek(ratio)) => player_interface.seek_to_ratio(ratio, app),
+                None => {},
+            }
         },
 
         // removed for now as it is currently unused
@@ -145,10 +312,59 @@ fn handle_user_input (mut app : &mut App, terminal : &mut Terminal<CrosstermBack
             //app.on_tick();
         }
 
-        // exit if the app should quit
+        // exit if the input thread stopped
         Err(_) => {
-            app.should_quit = true;
+            quit(app, terminal);
         }
     }
 
-}
\ No newline at end of file
+}
+
+// scrolls the help, actions that open something close it
+// the scroll position is limited while drawing
+fn scroll_help (app : &mut App, action : Action, count : u32)
+{
+    let count = count.min(u16::MAX as u32) as u16;
+
+    match action {
+        Action::Up => app.help_scroll = app.help_scroll.saturating_sub(count),
+        Action::Down => app.help_scroll = app.help_scroll.saturating_add(count),
+        Action::PageUp => app.help_scroll = app.help_scroll.saturating_sub(count.saturating_mul(10)),
+        Action::PageDown => app.help_scroll = app.help_scroll.saturating_add(count.saturating_mul(10)),
+        Action::First => app.help_scroll = 0,
+        Action::Last => app.help_scroll = u16::MAX,
+        Action::Help | Action::Quit | Action::Select => app.help_visible = false,
+        _ => {},
+    }
+}
+
+// runs a command of the ':' prompt or the keymap
+fn run_command_line (line : &str, app : &mut App, terminal : &mut Terminal<CrosstermBackend<std::io::Stdout>>, player_interface : &mut PlayerInterface)
+{
+    match command_line::parse(line) {
+        Ok(PromptCommand::Quit) => quit(app, terminal),
+        Ok(command) => player_interface.run_command(command, app),
+        Err(message) => app.message = message,
+    }
+}
+
+// tells the program to shut down and restores the terminal
+fn quit (app : &mut App, terminal : &mut Terminal<CrosstermBackend<std::io::Stdout>>)
+{
+    app.should_quit = true;
+
+    match disable_raw_mode() {
+        Ok(_) => {},
+        Err(_) => {println!("Error: Could not disable raw mode! Program is continuing regardless.")}
+    }
+
+    match terminal.show_cursor() {
+        Ok(_) => {},
+        Err(_) => println!("Error: Cursor could not be shown again! Program is continuing regardless."),
+    } 
+
+    match stdout().execute(DisableMouseCapture) {
+        Ok(_) => {},
+        Err(_) => println!("Error: mouse capture could not be disabled! Program is continuing regardless."),
+    }
+}
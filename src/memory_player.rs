@@ -0,0 +1,287 @@
+use std::time::{Duration, Instant};
+
+use crate::backend::{self, Backend, Track, Status, PlaybackState};
+
+// backend that keeps its playlists and queue in memory and only simulates playback
+// used to try out the UI without a server or audio device
+pub struct MemoryPlayer {
+    playlists: Vec<(String, Vec<Track>)>,
+    queue: Vec<Track>,
+    current: Option<usize>, // index of the current track in the queue
+    volume: i8,
+    repeat: bool, // only reported, the simulated playback ignores the options
+    random: bool,
+    single: bool,
+    started_at: Option<Instant>, // set while the current track is "playing"
+    elapsed_before: Duration, // playback time of the current track before started_at
+}
+
+impl MemoryPlayer {
+    pub fn new(playlists: Vec<(String, Vec<Track>)>) -> MemoryPlayer {
+        MemoryPlayer {
+            playlists,
+            queue: Vec::new(),
+            current: None,
+            volume: 100,
+            repeat: false,
+            random: false,
+            single: false,
+            started_at: None,
+            elapsed_before: Duration::from_secs(0),
+        }
+    }
+
+    // creates a player with a small made up library
+    pub fn with_demo_library() -> MemoryPlayer {
+        MemoryPlayer::new(demo_playlists())
+    }
+
+    fn start_track(&mut self, index: usize) {
+        self.current = Some(index);
+        self.elapsed_before = Duration::from_secs(0);
+        self.started_at = Some(Instant::now());
+    }
+
+    fn stop_track(&mut self) {
+        self.current = None;
+        self.started_at = None;
+        self.elapsed_before = Duration::from_secs(0);
+    }
+
+    fn elapsed(&self) -> Duration {
+        match self.started_at {
+            Some(started_at) => self.elapsed_before + started_at.elapsed(),
+            None => self.elapsed_before,
+        }
+    }
+
+    // advances the queue if the simulated track has ended
+    fn advance(&mut self) {
+        if let Some(i) = self.current {
+            let duration = Duration::from_secs(self.queue[i].duration.unwrap_or(0));
+            if self.started_at.is_some() && self.elapsed() >= duration {
+                if i + 1 < self.queue.len() {
+                    self.start_track(i + 1);
+                }
+                else {
+                    self.stop_track();
+                }
+            }
+        }
+    }
+}
+
+impl Backend for MemoryPlayer {
+    fn play(&mut self) -> Result<(), String> {
+        if self.current.is_none() {
+            if !self.queue.is_empty() {
+                self.start_track(0);
+            }
+        }
+        else if self.started_at.is_none() {
+            self.started_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<(), String> {
+        if let Some(started_at) = self.started_at.take() {
+            self.elapsed_before += started_at.elapsed();
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        self.stop_track();
+        Ok(())
+    }
+
+    fn next_song(&mut self) -> Result<(), String> {
+        if let Some(i) = self.current {
+            if i + 1 < self.queue.len() {
+                self.start_track(i + 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn prev_song(&mut self) -> Result<(), String> {
+        if let Some(i) = self.current {
+            self.start_track(i.saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    fn seek(&mut self, seconds: i64) -> Result<(), String> {
+        if self.current.is_some() {
+            self.elapsed_before = Duration::from_secs(seconds.max(0) as u64);
+            if self.started_at.is_some() {
+                self.started_at = Some(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    fn set_volume(&mut self, volume: i8) -> Result<(), String> {
+        self.volume = volume.clamp(0, 100);
+        Ok(())
+    }
+
+    fn set_repeat(&mut self, enabled: bool) -> Result<(), String> {
+        self.repeat = enabled;
+        Ok(())
+    }
+
+    fn set_random(&mut self, enabled: bool) -> Result<(), String> {
+        self.random = enabled;
+        Ok(())
+    }
+
+    fn set_single(&mut self, enabled: bool) -> Result<(), String> {
+        self.single = enabled;
+        Ok(())
+    }
+
+    fn clear_queue(&mut self) -> Result<(), String> {
+        self.stop_track();
+        self.queue.clear();
+        Ok(())
+    }
+
+    fn add_to_queue(&mut self, track: &Track) -> Result<(), String> {
+        self.queue.push(track.clone());
+        Ok(())
+    }
+
+    fn play_position(&mut self, position: u32) -> Result<(), String> {
+        if (position as usize) < self.queue.len() {
+            self.start_track(position as usize);
+        }
+        Ok(())
+    }
+
+    fn get_queue(&mut self) -> Result<Vec<Track>, String> {
+        Ok(self.queue.clone())
+    }
+
+    fn add_to_playlist(&mut self, playlist_name: &str, track: &Track) -> Result<(), String> {
+        match self.playlists.iter_mut().find(|(name, _)| name == playlist_name.trim()) {
+            Some((_, tracks)) => tracks.push(track.clone()),
+            None => self.playlists.push((playlist_name.trim().to_string(), vec![track.clone()])),
+        }
+        Ok(())
+    }
+
+    fn save_queue(&mut self, playlist_name: &str) -> Result<(), String> {
+        let name = playlist_name.trim();
+        self.playlists.retain(|(n, _)| n != name);
+        self.playlists.push((name.to_string(), self.queue.clone()));
+        Ok(())
+    }
+
+    fn remove_from_playlist(&mut self, playlist_name: &str, positions: &[u32]) -> Result<(), String> {
+        match self.playlists.iter_mut().find(|(name, _)| name == playlist_name.trim()) {
+            Some((_, tracks)) => backend::remove_positions(tracks, positions),
+            None => Err(format!("no playlist \"{}\"", playlist_name.trim())),
+        }
+    }
+
+    fn move_in_playlist(&mut self, playlist_name: &str, from: u32, to: u32) -> Result<(), String> {
+        match self.playlists.iter_mut().find(|(name, _)| name == playlist_name.trim()) {
+            Some((_, tracks)) => backend::move_position(tracks, from, to),
+            None => Err(format!("no playlist \"{}\"", playlist_name.trim())),
+        }
+    }
+
+    fn load_playlist(&mut self, playlist_name: &str, start: u32, end: u32) -> Result<(), String> {
+        let tracks = self.get_all_tracks_in_playlist(playlist_name)?;
+        let end = (end as usize).min(tracks.len());
+        let start = (start as usize).min(end);
+        self.queue.extend_from_slice(&tracks[start..end]);
+        Ok(())
+    }
+
+    fn get_all_playlist_names(&mut self) -> Result<Vec<String>, String> {
+        Ok(self.playlists.iter().map(|(name, _)| name.clone()).collect())
+    }
+
+    fn get_all_tracks_in_playlist(&mut self, playlist_name: &str) -> Result<Vec<Track>, String> {
+        match self.playlists.iter().find(|(name, _)| name == playlist_name.trim()) {
+            Some((_, tracks)) => Ok(tracks.clone()),
+            None => Err(format!("no playlist \"{}\"", playlist_name.trim())),
+        }
+    }
+
+    // the demo library has no database, so all tracks of the playlists are used
+    fn get_library(&mut self) -> Result<Vec<Track>, String> {
+        let mut tracks: Vec<Track> = Vec::new();
+        for (_, playlist) in self.playlists.iter() {
+            for track in playlist.iter() {
+                if !tracks.iter().any(|t| t.file == track.file) {
+                    tracks.push(track.clone());
+                }
+            }
+        }
+
+        Ok(tracks)
+    }
+
+    fn get_status(&mut self) -> Result<Status, String> {
+        self.advance();
+
+        let state = if self.started_at.is_some() {
+            PlaybackState::Play
+        }
+        else if self.current.is_some() {
+            PlaybackState::Pause
+        }
+        else {
+            PlaybackState::Stop
+        };
+
+        Ok(Status {
+            state,
+            volume: self.volume,
+            elapsed: self.elapsed().as_secs() as i64,
+            queue_position: self.current.map(|i| i as u32),
+            queue_length: self.queue.len() as u32,
+            repeat: self.repeat,
+            random: self.random,
+            single: self.single,
+            consume: false,
+        })
+    }
+
+    fn get_current_track(&mut self) -> Result<Option<Track>, String> {
+        self.advance();
+        Ok(self.current.map(|i| self.queue[i].clone()))
+    }
+
+    fn close_conn(&mut self) {
+        self.stop_track();
+    }
+}
+
+// a small made up library, grouped into playlists
+pub fn demo_playlists() -> Vec<(String, Vec<Track>)> {
+    let track = |title: &str, artist: &str, album: &str, duration: u64| Track {
+        file: format!("{}/{}/{}.flac", artist, album, title),
+        title: Some(title.to_string()),
+        artist: Some(artist.to_string()),
+        album: Some(album.to_string()),
+        duration: Some(duration),
+        ..Track::default()
+    };
+
+    vec![
+        (String::from("Evening"), vec![
+            track("Slow Tide", "The Harbour Lights", "Low Water", 214),
+            track("Lantern", "The Harbour Lights", "Low Water", 187),
+            track("Night Ferry", "Mira Kell", "Crossings", 263),
+        ]),
+        (String::from("Workout"), vec![
+            track("Sprint", "Volt Season", "Pulse", 176),
+            track("Second Wind", "Volt Season", "Pulse", 201),
+        ]),
+    ]
+}
@@ -0,0 +1,187 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{MouseButton, MouseEvent};
+use tui::layout::Rect;
+
+use crate::util::app::App;
+use crate::util::stateful_selected_list::{CurrentElement, StatefulSelectedList};
+
+// two clicks on the same row within this time are a double click
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+// screen areas of the blocks, set while drawing and used to find the clicked block
+// the playbar buttons and the timeline are stored without their borders
+#[derive(Default)]
+pub struct BlockAreas {
+    pub views: Rect,
+    pub playlists: Rect,
+    pub tabs: Rect,
+    pub timeline: Rect,
+    pub main_area: Rect,
+}
+
+// actions of a mouse event that need the player
+pub enum MouseCommand {
+    Select, // same as the select key on the focused block
+    Seek(f64), // position in the current song, between 0 and 1
+}
+
+// last click, used to detect double clicks
+pub struct Click {
+    time: Instant,
+    column: u16,
+    row: u16,
+}
+
+// focuses and selects what was clicked, returns what the player has to do
+pub fn handle_mouse(app: &mut App, event: MouseEvent) -> Option<MouseCommand> {
+    match event {
+        MouseEvent::Down(MouseButton::Left, column, row, _) => {
+            let double = match &app.last_click {
+                Some(click) => click.column == column && click.row == row && click.time.elapsed() < DOUBLE_CLICK_TIME,
+                None => false,
+            };
+            // a third click starts a new double click
+            app.last_click = if double { None } else { Some(Click { time: Instant::now(), column, row }) };
+
+            click(app, column, row, double)
+        },
+        // the timeline can be dragged, other blocks ignore it
+        MouseEvent::Drag(MouseButton::Left, column, _, _) => {
+            if app.current_element == CurrentElement::Timeline {
+                return Some(MouseCommand::Seek(ratio(app.areas.timeline, column)));
+            }
+            None
+        },
+        MouseEvent::ScrollDown(column, row, _) => {
+            scroll(app, column, row, true);
+            None
+        },
+        MouseEvent::ScrollUp(column, row, _) => {
+            scroll(app, column, row, false);
+            None
+        },
+        _ => None,
+    }
+}
+
+fn click(app: &mut App, column: u16, row: u16, double: bool) -> Option<MouseCommand> {
+    app.playbar_state.index = 3;
+
+    if contains(app.areas.views, column, row) {
+        app.current_element = CurrentElement::Views;
+        // a single click switches the view, like the tabs of the playbar
+        if select_row(&mut app.view_list, app.areas.views, row) {
+            return Some(MouseCommand::Select);
+        }
+    }
+    else if contains(app.areas.playlists, column, row) {
+        app.current_element = CurrentElement::Playlists;
+        if select_row(&mut app.playlist_list, app.areas.playlists, row) && double {
+            return Some(MouseCommand::Select);
+        }
+    }
+    else if contains(app.areas.main_area, column, row) {
+        app.current_element = CurrentElement::MainArea;
+        // the header of the table and the line below it are no rows
+        if let Some(index) = clicked_row(app.table.offset(), app.table.len(), app.areas.main_area, 2, row) {
+            app.table.select(index);
+            if double {
+                return Some(MouseCommand::Select);
+            }
+        }
+    }
+    else if contains(app.areas.tabs, column, row) {
+        app.current_element = CurrentElement::Playbar;
+        if let Some(index) = tab_at(app, column) {
+            app.playbar_state.index = index;
+            return Some(MouseCommand::Select);
+        }
+        app.playbar_state.index = 0;
+    }
+    else if contains(app.areas.timeline, column, row) {
+        app.current_element = CurrentElement::Timeline;
+        return Some(MouseCommand::Seek(ratio(app.areas.timeline, column)));
+    }
+
+    None
+}
+
+// moves the selection of the list under the pointer
+fn scroll(app: &mut App, column: u16, row: u16, down: bool) {
+    let step = |selected: usize| if down { selected + 1 } else { selected.saturating_sub(1) };
+
+    if contains(app.areas.views, column, row) {
+        let selected = app.view_list.get_selected_index();
+        app.view_list.select(step(selected));
+        app.current_element = CurrentElement::Views;
+    }
+    else if contains(app.areas.playlists, column, row) {
+        let selected = app.playlist_list.get_selected_index();
+        app.playlist_list.select(step(selected));
+        app.current_element = CurrentElement::Playlists;
+    }
+    else if contains(app.areas.main_area, column, row) {
+        app.table.select(step(app.table.selected()));
+        app.current_element = CurrentElement::MainArea;
+    }
+    else {
+        return;
+    }
+
+    app.playbar_state.index = 3;
+}
+
+// selects the clicked row of a list, returns false if the click was not on an element
+fn select_row(list: &mut StatefulSelectedList, area: Rect, row: u16) -> bool {
+    match clicked_row(list.get_offset(), list.len(), area, 0, row) {
+        Some(index) => {
+            list.select(index);
+            true
+        },
+        None => false,
+    }
+}
+
+// index of the clicked element, the first row is the border and the header is skipped
+fn clicked_row(offset: usize, length: usize, area: Rect, header: u16, row: u16) -> Option<usize> {
+    if row <= area.y + header || row >= area.y + area.height - 1 {
+        return None;
+    }
+
+    let index = offset + (row - area.y - 1 - header) as usize;
+    if index >= length {
+        return None;
+    }
+
+    Some(index)
+}
+
+// finds the clicked playbar button
+// tabs draws a space, the title, a space and the divider for every button
+fn tab_at(app: &App, column: u16) -> Option<usize> {
+    let mut x = app.areas.tabs.x;
+
+    for (index, title) in app.playbar_state.titles.iter().enumerate() {
+        let width = title.chars().count() as u16;
+        // the padding around a title belongs to it
+        if column >= x && column <= x + width + 1 {
+            return Some(index);
+        }
+        x += width + 3;
+    }
+
+    None
+}
+
+// position of the column in the timeline between 0 and 1
+fn ratio(area: Rect, column: u16) -> f64 {
+    let width = area.width.max(1);
+    let offset = column.saturating_sub(area.x).min(width);
+
+    offset as f64 / width as f64
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
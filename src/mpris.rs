@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::Connection;
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::channel::{MatchingReceiver, Sender as DbusSender};
+use dbus::message::{MatchRule, SignalArgs};
+use dbus::{Message, Path};
+use dbus_crossroads::{Crossroads, IfaceBuilder, MethodErr};
+
+use crate::backend::{Track, Status, PlaybackState};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.rust_cmp";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+// a jump of the position by more than this is reported as seek
+const SEEK_TOLERANCE_US: i64 = 2_000_000;
+
+// requests received over D-Bus, they are executed by the main loop
+#[derive(Clone, PartialEq, Debug)]
+pub enum MprisCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    Seek(i64), // relative offset in microseconds
+    SetPosition(i64), // absolute position in microseconds
+    SetVolume(f64), // 0.0 - 1.0
+    SetLoopStatus(String), // "None", "Track" or "Playlist"
+    SetShuffle(bool),
+    Quit,
+}
+
+// the player state as it is exposed on the bus
+#[derive(Clone, PartialEq, Default)]
+struct Snapshot {
+    playback_status: String,
+    loop_status: String,
+    shuffle: bool,
+    volume: f64,
+    position: i64, // microseconds
+    track: Option<Track>,
+    track_position: u32, // position of the track in the queue, used for the track id
+}
+
+impl Snapshot {
+    fn track_id(&self) -> Path<'static> {
+        match &self.track {
+            Some(_) => Path::from(format!("/org/rust_cmp/track/{}", self.track_position)),
+            None => Path::from("/org/mpris/MediaPlayer2/TrackList/NoTrack"),
+        }
+    }
+
+    fn metadata(&self) -> PropMap {
+        let mut metadata: PropMap = HashMap::new();
+        metadata.insert(String::from("mpris:trackid"), Variant(Box::new(self.track_id()) as Box<dyn RefArg>));
+
+        if let Some(track) = &self.track {
+            metadata.insert(String::from("xesam:title"), Variant(Box::new(track.get_title())));
+            metadata.insert(String::from("xesam:url"), Variant(Box::new(track.file.clone())));
+            if let Some(artist) = &track.artist {
+                metadata.insert(String::from("xesam:artist"), Variant(Box::new(vec![artist.clone()])));
+            }
+            if let Some(album_artist) = &track.album_artist {
+                metadata.insert(String::from("xesam:albumArtist"), Variant(Box::new(vec![album_artist.clone()])));
+            }
+            if let Some(album) = &track.album {
+                metadata.insert(String::from("xesam:album"), Variant(Box::new(album.clone())));
+            }
+            if let Some(duration) = track.duration {
+                metadata.insert(String::from("mpris:length"), Variant(Box::new(duration as i64 * 1_000_000)));
+            }
+        }
+
+        metadata
+    }
+
+    // properties that differ from the other snapshot, used for PropertiesChanged
+    fn changed_properties(&self, old: &Snapshot) -> PropMap {
+        let mut changed: PropMap = HashMap::new();
+
+        if self.playback_status != old.playback_status {
+            changed.insert(String::from("PlaybackStatus"), Variant(Box::new(self.playback_status.clone())));
+        }
+        if self.loop_status != old.loop_status {
+            changed.insert(String::from("LoopStatus"), Variant(Box::new(self.loop_status.clone())));
+        }
+        if self.shuffle != old.shuffle {
+            changed.insert(String::from("Shuffle"), Variant(Box::new(self.shuffle)));
+        }
+        if self.volume != old.volume {
+            changed.insert(String::from("Volume"), Variant(Box::new(self.volume)));
+        }
+        if self.track != old.track || self.track_position != old.track_position {
+            changed.insert(String::from("Metadata"), Variant(Box::new(self.metadata())));
+        }
+
+        changed
+    }
+}
+
+// state shared between the D-Bus thread and the method handlers
+#[derive(Clone)]
+struct Shared {
+    snapshot: Arc<Mutex<Snapshot>>,
+    commands: Sender<MprisCommand>,
+}
+
+impl Shared {
+    fn send(&self, command: MprisCommand) -> Result<(), MethodErr> {
+        self.commands.send(command).map_err(|_| MethodErr::failed("player has shut down"))
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+}
+
+// exposes the player as org.mpris.MediaPlayer2 service on the session bus
+pub struct MprisServer {
+    snapshot: Arc<Mutex<Snapshot>>,
+    commands: Receiver<MprisCommand>,
+}
+
+impl MprisServer {
+    // connects to the session bus and serves the interface in a background thread
+    // returns None if there is no session bus
+    pub fn start() -> Option<MprisServer> {
+        let connection = Connection::new_session().ok()?;
+        connection.request_name(BUS_NAME, false, true, false).ok()?;
+
+        let (tx, rx) = channel();
+        let shared = Shared {
+            snapshot: Arc::new(Mutex::new(Snapshot::default())),
+            commands: tx,
+        };
+        let server = MprisServer {
+            snapshot: shared.snapshot.clone(),
+            commands: rx,
+        };
+
+        thread::spawn(move || serve(connection, shared));
+
+        Some(server)
+    }
+
+    // publishes the current state of the player
+    pub fn update(&self, status: &Status, track: Option<Track>) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+
+        snapshot.playback_status = String::from(match status.state {
+            PlaybackState::Play => "Playing",
+            PlaybackState::Pause => "Paused",
+            PlaybackState::Stop => "Stopped",
+        });
+        snapshot.loop_status = String::from(match (status.repeat, status.single) {
+            (false, _) => "None",
+            (true, true) => "Track",
+            (true, false) => "Playlist",
+        });
+        snapshot.shuffle = status.random;
+        snapshot.volume = status.volume.max(0) as f64 / 100.0;
+        snapshot.position = status.elapsed * 1_000_000;
+        snapshot.track_position = status.queue_position.unwrap_or(0);
+        snapshot.track = track;
+    }
+
+    // returns all requests received since the last call
+    pub fn poll_commands(&self) -> Vec<MprisCommand> {
+        self.commands.try_iter().collect()
+    }
+}
+
+// registers the interfaces and handles requests until the player shuts down
+fn serve(connection: Connection, shared: Shared) {
+    let mut crossroads = Crossroads::new();
+
+    let root = crossroads.register(ROOT_INTERFACE, |b: &mut IfaceBuilder<Shared>| {
+        b.method("Raise", (), (), |_, _, _: ()| Ok(()));
+        b.method("Quit", (), (), |_, shared, _: ()| shared.send(MprisCommand::Quit));
+        b.property("CanQuit").get(|_, _| Ok(true));
+        b.property("CanRaise").get(|_, _| Ok(false));
+        b.property("HasTrackList").get(|_, _| Ok(false));
+        b.property("Identity").get(|_, _| Ok(String::from("rust-cmp")));
+        b.property("SupportedUriSchemes").get(|_, _| Ok(vec![String::from("file"), String::from("http"), String::from("https")]));
+        b.property("SupportedMimeTypes").get(|_, _| Ok(Vec::<String>::new()));
+    });
+
+    let player = crossroads.register(PLAYER_INTERFACE, |b: &mut IfaceBuilder<Shared>| {
+        b.method("Play", (), (), |_, shared, _: ()| shared.send(MprisCommand::Play));
+        b.method("Pause", (), (), |_, shared, _: ()| shared.send(MprisCommand::Pause));
+        b.method("PlayPause", (), (), |_, shared, _: ()| shared.send(MprisCommand::PlayPause));
+        b.method("Stop", (), (), |_, shared, _: ()| shared.send(MprisCommand::Stop));
+        b.method("Next", (), (), |_, shared, _: ()| shared.send(MprisCommand::Next));
+        b.method("Previous", (), (), |_, shared, _: ()| shared.send(MprisCommand::Previous));
+        b.method("Seek", ("Offset",), (), |_, shared, (offset,): (i64,)| shared.send(MprisCommand::Seek(offset)));
+        b.method("SetPosition", ("TrackId", "Position"), (), |_, shared, (track_id, position): (Path<'static>, i64)| {
+            // the request is ignored if the track has changed in the meantime
+            if track_id == shared.snapshot().track_id() {
+                shared.send(MprisCommand::SetPosition(position))?;
+            }
+            Ok(())
+        });
+        b.method("OpenUri", ("Uri",), (), |_, _, _: (String,)| -> Result<(), MethodErr> {
+            Err(MethodErr::failed("opening uris is not supported"))
+        });
+        b.signal::<(i64,), _>("Seeked", ("Position",));
+
+        b.property("PlaybackStatus").get(|_, shared| Ok(shared.snapshot().playback_status));
+        b.property("LoopStatus")
+            .get(|_, shared| Ok(shared.snapshot().loop_status))
+            .set(|_, shared, value: String| {
+                shared.send(MprisCommand::SetLoopStatus(value.clone()))?;
+                Ok(Some(value))
+            });
+        b.property("Shuffle")
+            .get(|_, shared| Ok(shared.snapshot().shuffle))
+            .set(|_, shared, value: bool| {
+                shared.send(MprisCommand::SetShuffle(value))?;
+                Ok(Some(value))
+            });
+        b.property("Volume")
+            .get(|_, shared| Ok(shared.snapshot().volume))
+            .set(|_, shared, value: f64| {
+                shared.send(MprisCommand::SetVolume(value))?;
+                Ok(Some(value))
+            });
+        b.property("Metadata").get(|_, shared| Ok(shared.snapshot().metadata()));
+        b.property("Position").emits_changed_false().get(|_, shared| Ok(shared.snapshot().position));
+        b.property("Rate").get(|_, _| Ok(1.0f64));
+        b.property("MinimumRate").get(|_, _| Ok(1.0f64));
+        b.property("MaximumRate").get(|_, _| Ok(1.0f64));
+        b.property("CanGoNext").get(|_, _| Ok(true));
+        b.property("CanGoPrevious").get(|_, _| Ok(true));
+        b.property("CanPlay").get(|_, _| Ok(true));
+        b.property("CanPause").get(|_, _| Ok(true));
+        b.property("CanSeek").get(|_, shared| Ok(shared.snapshot().track.map(|t| t.duration.is_some()).unwrap_or(false)));
+        b.property("CanControl").get(|_, _| Ok(true));
+    });
+
+    crossroads.insert(OBJECT_PATH, &[root, player], shared.clone());
+
+    connection.start_receive(MatchRule::new_method_call(), Box::new(move |message, connection| {
+        let _ = crossroads.handle_message(message, connection);
+        true
+    }));
+
+    let path = Path::from(OBJECT_PATH);
+    let mut last = shared.snapshot();
+
+    loop {
+        if connection.process(Duration::from_millis(250)).is_err() {
+            return;
+        }
+
+        let current = shared.snapshot();
+
+        // clients are told about changed properties, the position is only sent when it jumps
+        let changed = current.changed_properties(&last);
+        if !changed.is_empty() {
+            let signal = PropertiesPropertiesChanged {
+                interface_name: String::from(PLAYER_INTERFACE),
+                changed_properties: changed,
+                invalidated_properties: Vec::new(),
+            };
+            let _ = connection.send(signal.to_emit_message(&path));
+        }
+
+        let expected_position = last.position + 250_000;
+        if current.track == last.track && (current.position - expected_position).abs() > SEEK_TOLERANCE_US {
+            let signal = Message::signal(&path, &PLAYER_INTERFACE.into(), &"Seeked".into()).append1(current.position);
+            let _ = connection.send(signal);
+        }
+
+        last = current;
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::Connection;
+
+use crate::backend::Track;
+use crate::util::paths::cache_dir;
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+// the player must not hang if the notification daemon does not respond
+const CALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+// how long a notification is shown in milliseconds
+const EXPIRE_TIMEOUT: i32 = 5000;
+
+// shows a desktop notification when the playing song changes
+pub struct Notifier {
+    connection: Connection,
+    only_unfocused: bool, // notify only if the terminal is not the focused window
+    last_song: Option<(String, String)>, // file and title of the last notified song
+    notification_id: u32, // the previous notification is replaced instead of stacking up
+}
+
+impl Notifier {
+    // returns None if there is no session bus
+    pub fn new(only_unfocused: bool) -> Option<Notifier> {
+        let connection = Connection::new_session().ok()?;
+
+        Some(Notifier {
+            connection,
+            only_unfocused,
+            last_song: None,
+            notification_id: 0,
+        })
+    }
+
+    // applies the settings of a reloaded config, the last song is kept so it is not notified again
+    pub fn set_only_unfocused(&mut self, only_unfocused: bool) {
+        self.only_unfocused = only_unfocused;
+    }
+
+    // should be called with the current track on every update
+    // a notification is only shown once per song, the cover is only asked for then
+    pub fn track_changed<F>(&mut self, track: &Track, cover_art: F) where F: FnOnce() -> Option<Vec<u8>> {
+        // streams keep their file, so the live title is part of the key
+        let song = (track.file.clone(), track.get_title());
+        if self.last_song.as_ref() == Some(&song) {
+            return;
+        }
+        self.last_song = Some(song);
+
+        if self.only_unfocused && terminal_focused() {
+            return;
+        }
+
+        let (summary, body) = if track.is_stream() {
+            (track.title.clone().unwrap_or(String::from("live stream")), track.name.clone().unwrap_or(track.file.clone()))
+        }
+        else {
+            (track.get_title(), format!("{}\n{}", track.get_artist(), track.get_album()))
+        };
+
+        let mut hints: PropMap = HashMap::new();
+        hints.insert(String::from("category"), Variant(Box::new(String::from("x-gnome.music")) as Box<dyn RefArg>));
+        if let Some(cover) = cover_art().and_then(|image| save_cover(&image)) {
+            hints.insert(String::from("image-path"), Variant(Box::new(cover)));
+        }
+
+        let proxy = self.connection.with_proxy(NOTIFICATIONS_NAME, NOTIFICATIONS_PATH, CALL_TIMEOUT);
+        let result: Result<(u32,), dbus::Error> = proxy.method_call(NOTIFICATIONS_NAME, "Notify", (
+            "rust-cmp",
+            self.notification_id,
+            "audio-x-generic",
+            summary,
+            body,
+            Vec::<String>::new(),
+            hints,
+            EXPIRE_TIMEOUT,
+        ));
+
+        // notifications are optional, so errors are ignored
+        if let Ok((id,)) = result {
+            self.notification_id = id;
+        }
+    }
+}
+
+// the notification daemon reads the cover from a file, which is overwritten for every song
+// returns the url of the file
+fn save_cover(image: &[u8]) -> Option<String> {
+    let dir = cache_dir()?;
+    fs::create_dir_all(&dir).ok()?;
+    let path = dir.join("cover");
+    fs::write(&path, image).ok()?;
+
+    Some(format!("file://{}", path.to_string_lossy()))
+}
+
+// returns true if the window of the terminal is focused
+// this needs X11 with xdotool, elsewhere the terminal is assumed to be unfocused
+fn terminal_focused() -> bool {
+    let window_id = match env::var("WINDOWID") {
+        Ok(id) => id,
+        Err(_) => return false,
+    };
+
+    match Command::new("xdotool").arg("getactivewindow").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == window_id.trim(),
+        Err(_) => false,
+    }
+}
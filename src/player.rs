@@ -1,220 +1,293 @@
 extern crate mpd;
 
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
 use mpd::client::*;
 use mpd::State;
 use mpd::Song;
-use mpd::Query;
+use mpd::{Query, Term};
+use mpd::idle::{Idle, Subsystem};
+
+use crate::backend::{Backend, Track, Status, PlaybackState};
 
 pub struct Player {
     client: Client,
+    address: String, // used for the requests the mpd crate does not support, see raw_commands
 }
 
 impl Player {
     // create new Player object with given adress
-    pub fn new(ip_with_port: &String) -> Player {
-        Player {
-            // try to connect to the client
-            client: match Client::connect(ip_with_port) { 
-                Ok(c) => c, // connection successfully established
-                Err(_) => { println!("Error connecting to the server with address: {}", ip_with_port); std::process::exit(1); }, // error
-            }
+    // exits the program if the connection fails
+    pub fn new(ip_with_port: &str) -> Player {
+        match Player::connect(ip_with_port) {
+            Ok(p) => p, // connection successfully established
+            Err(_) => { println!("Error connecting to the server with address: {}", ip_with_port); std::process::exit(1); }, // error
         }
     }
 
-    // creates a new connection to the given adress
-    #[allow(dead_code)]
-    pub fn new_conn(& mut self, address: &String) {
-        match Client::connect(address) {
-            Ok(t) => self.client = t,
-            Err(_) => println!("Error connecting to adress: {}", address),
-        }
+    // try to connect to the server with the given adress
+    pub fn connect(ip_with_port: &str) -> Result<Player, mpd::error::Error> {
+        Ok(Player {
+            client: Client::connect(ip_with_port)?,
+            address: ip_with_port.to_string(),
+        })
     }
 
-    // starts playback on the server if not already playing
-    pub fn play(& mut self) {
-        if self.client.status().unwrap().state == State::Play {
-            println!("Already playing");
-        }
-        else {
-            self.client.play().unwrap();
-        }
+    // blocks until something changes on the server and returns the changed subsystems
+    pub fn wait_for_changes(&mut self) -> Result<Vec<Subsystem>, mpd::error::Error> {
+        self.client.wait(&[])
     }
 
-    // pauses playback if not already paused
-    pub fn pause(& mut self) {
-        if self.client.status().unwrap().state == State::Pause {
-            println!("Already paused");
-        }
-        else {
-            self.client.toggle_pause().unwrap();
+    // sends the commands as one command list over a new connection and returns the
+    // key value pairs of the response, the mpd crate has no command lists and hides
+    // some fields (e.g. the plugin of the outputs)
+    fn raw_commands(&self, commands: &[String]) -> Result<Vec<(String, String)>, String> {
+        let stream = TcpStream::connect(&self.address).map_err(|e| e.to_string())?;
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(stream).lines();
+
+        match lines.next() {
+            Some(Ok(greeting)) if greeting.starts_with("OK MPD") => {},
+            _ => return Err(String::from("the server did not greet like mpd")),
         }
-    }
 
-    // Play if paused, pause if playing
-    // Used for the combined play/pause button
-    pub fn toggle_play_pause(& mut self) {
-        if self.client.status().unwrap().state == State::Pause {
-            self.play();
+        let mut request = String::from("command_list_begin\n");
+        for command in commands {
+            request.push_str(command);
+            request.push('\n');
         }
-        else if self.client.status().unwrap().state == State::Play {
-            self.pause();
+        request.push_str("command_list_end\nclose\n");
+        writer.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            // errors look like "ACK [50@0] {add} No such directory"
+            if line.starts_with("ACK ") {
+                return Err(line.split_once("} ").map(|(_, message)| message).unwrap_or(&line).to_string());
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
         }
-    }
 
+        Err(String::from("the connection to the server was closed"))
+    }
+}
 
-    // returns true if a track is currently playing
-    pub fn is_playing(&mut self) -> bool {
-        return self.client.status().unwrap().state == State::Play
+impl Backend for Player {
+    // starts or resumes playback, nothing happens if the server is already playing
+    fn play(& mut self) -> Result<(), String> {
+        self.client.play().map_err(|e| e.to_string())
     }
 
-    // clears current queue
-    pub fn clear_queue(&mut self) {
-        self.client.clear().unwrap();
+    // pauses playback, nothing happens if the server is paused or stopped
+    fn pause(& mut self) -> Result<(), String> {
+        self.client.pause(true).map_err(|e| e.to_string())
     }
 
-    #[allow(dead_code)]
-    pub fn insert_as_first(&mut self, song: Song) {
-        self.client.insert(song, 0).unwrap();
+    fn stop(&mut self) -> Result<(), String> {
+        self.client.stop().map_err(|e| e.to_string())
     }
 
-    // add a song to a queue
-    #[allow(dead_code)]
-    pub fn add_to_queue(& mut self, song: Song) {
-        self.client.push(song).unwrap();
+    // switch current song to next song in queue
+    fn next_song(&mut self) -> Result<(), String> {
+        self.client.next().map_err(|e| e.to_string())
     }
 
-    // get all names of all playlists
-    pub fn get_all_playlist_names(& mut self) -> Vec<String> {
-        let playlists = self.client.playlists().unwrap();
-        let mut return_vec: Vec<String> = Vec::new();
-        for playlist in playlists {
-            return_vec.push(playlist.name)
-        }
+    // switch current song to prev song in queue
+    fn prev_song(&mut self) -> Result<(), String> {
+        self.client.prev().map_err(|e| e.to_string())
+    }
 
-        return_vec
+    // seek to 'seconds' seconds in current song
+    fn seek(&mut self, seconds: i64) -> Result<(), String> {
+        self.client.rewind(seconds).map_err(|e| e.to_string())
     }
 
-    // get all titles in a specific playlist
-    pub fn get_all_titles_in_playlist(& mut self, playlist_name: &String) -> Vec<String> {
-        let songs = self.client.playlist(playlist_name.trim()).unwrap();
-        let mut ret_songs: Vec<String> = Vec::new();
-        for song in songs {
-            if song.title.is_some() {
-                ret_songs.push(song.title.unwrap());
-            }
-            else {
-                ret_songs.push(String::from("unknown title"));
-            }
-        }
+    // set playback volume
+    fn set_volume(&mut self, volume: i8) -> Result<(), String> {
+        self.client.volume(volume).map_err(|e| e.to_string())
+    }
 
-        ret_songs
+    fn set_repeat(&mut self, enabled: bool) -> Result<(), String> {
+        self.client.repeat(enabled).map_err(|e| e.to_string())
     }
 
-    // get all song objects in a playlist
-    pub fn get_all_songs_in_playlist(&mut self, playlist_name: &String) -> Vec<Song> {
-        self.client.playlist(playlist_name.trim()).unwrap()
+    fn set_random(&mut self, enabled: bool) -> Result<(), String> {
+        self.client.random(enabled).map_err(|e| e.to_string())
     }
 
-    // set playback volume
-    #[allow(dead_code)]
-    pub fn set_volume(&mut self, volume: i8) {
-        self.client.volume(volume).unwrap();
+    fn set_single(&mut self, enabled: bool) -> Result<(), String> {
+        self.client.single(enabled).map_err(|e| e.to_string())
     }
 
-    // switch current song to next song in queue
-    pub fn next_song(&mut self) {
-        self.client.next().unwrap(); 
+    // clears current queue
+    fn clear_queue(&mut self) -> Result<(), String> {
+        self.client.clear().map_err(|e| e.to_string())
     }
 
-    // switch current song to prev song in queue
-    pub fn prev_song(&mut self) {
-        self.client.prev().unwrap();
+    // add a song to a queue
+    fn add_to_queue(& mut self, track: &Track) -> Result<(), String> {
+        let song = Song { file: track.file.clone(), ..Song::default() };
+        self.client.push(song).map(|_| ()).map_err(|e| e.to_string())
     }
 
-    // get vector of all songs in the current queue
-    #[allow(dead_code)]
-    pub fn get_songs_in_queue(&mut self) -> Vec<String> {
-        let songs = self.client.queue().unwrap();
-        let mut ret_songs: Vec<String> = Vec::new();
-        for song in songs {
-            ret_songs.push(song.title.unwrap());
+    // one command list instead of a request per track
+    fn add_all_to_queue(&mut self, tracks: &[Track]) -> Result<(), String> {
+        if tracks.is_empty() {
+            return Ok(());
         }
+        let commands: Vec<String> = tracks.iter().map(|track| format!("add {}", quote(&track.file))).collect();
+        self.raw_commands(&commands).map(|_| ())
+    }
 
-        ret_songs
+    fn add_to_playlist(&mut self, playlist_name: &str, track: &Track) -> Result<(), String> {
+        let song = Song { file: track.file.clone(), ..Song::default() };
+        self.client.pl_push(playlist_name.trim(), song).map_err(|e| e.to_string())
     }
 
-    // seek to 'seconds' seconds in current song 
-    #[allow(dead_code)]
-    pub fn seek(&mut self, seconds: i64) {
-        self.client.rewind(seconds).unwrap();
+    // the server does not overwrite playlists, so an existing one is removed first
+    fn save_queue(&mut self, playlist_name: &str) -> Result<(), String> {
+        let name = playlist_name.trim();
+        if self.get_all_playlist_names()?.iter().any(|n| n == name) {
+            self.client.pl_remove(name).map_err(|e| e.to_string())?;
+        }
+        self.client.save(name).map_err(|e| e.to_string())
     }
 
-    // get title of current song
-    #[allow(dead_code)]
-    pub fn get_current_song_title(&mut self) -> String {
-        let song_title: String;
-        if self.client.currentsong().unwrap().is_some() {
-            let song: Song = self.client.currentsong().unwrap().unwrap();
-            song_title = song.title.unwrap();
-        } else
-        {
-            song_title = String::from("unknown title");
+    fn remove_from_playlist(&mut self, playlist_name: &str, positions: &[u32]) -> Result<(), String> {
+        let mut positions = positions.to_vec();
+        positions.sort_unstable();
+        positions.dedup();
+
+        // deleting from the end keeps the other positions valid
+        for position in positions.into_iter().rev() {
+            self.client.pl_delete(playlist_name.trim(), position).map_err(|e| e.to_string())?;
         }
-        
-        song_title
+        Ok(())
     }
 
-    // get all song objects in db 
-    #[allow(dead_code)]
-    pub fn get_all_songs(&mut self, ) -> Vec<Song> {
-        let songs = self.client.search(&Query::new(), (0, 0)).unwrap();
+    fn move_in_playlist(&mut self, playlist_name: &str, from: u32, to: u32) -> Result<(), String> {
+        self.client.pl_shift(playlist_name.trim(), from, to).map_err(|e| e.to_string())
+    }
 
-        songs
+    fn play_position(&mut self, position: u32) -> Result<(), String> {
+        self.client.switch(position).map_err(|e| e.to_string())
     }
 
-    // get all song titles in db
-    #[allow(dead_code)]
-    pub fn get_all_song_titles(&mut self) -> Vec<String> {
-        let songs = self.client.search(&Query::new(), (0, 1));
-        let songs_ = songs.unwrap();
-        let mut song_titles: Vec<String> = Vec::new();
-        for song in songs_ {
-            song_titles.push(song.title.unwrap());
-        }
+    fn get_queue(&mut self) -> Result<Vec<Track>, String> {
+        let songs = self.client.queue().map_err(|e| e.to_string())?;
+        Ok(songs.into_iter().map(track_from_song).collect())
+    }
 
-        song_titles
+    // loads a playlist into the queue
+    fn load_playlist (&mut self, playlist_name: &str, start: u32,  end: u32) -> Result<(), String> {
+        let name = playlist_name.trim();
+        self.client.load(name, start..end).map_err(|e| e.to_string())
     }
 
-    // get Song object of current song
-    #[allow(dead_code)]
-    pub fn get_current_song(&mut self) -> Song {
-        if self.client.currentsong().unwrap().is_some() {
-            self.client.currentsong().unwrap().unwrap()
-        } else
-        {
-            panic!();
+    // get all names of all playlists
+    fn get_all_playlist_names(& mut self) -> Result<Vec<String>, String> {
+        let playlists = self.client.playlists().map_err(|e| e.to_string())?;
+        let mut return_vec: Vec<String> = Vec::new();
+        for playlist in playlists {
+            return_vec.push(playlist.name)
         }
 
+        Ok(return_vec)
     }
 
-    // loads a playlist into the queue
-    pub fn load_playlist (&mut self, playlist_name: &String, start: u32,  end: u32) {
-        let name = playlist_name.trim();
-        self.client.load(name, start..end).unwrap();
+    // get all tracks in a playlist
+    fn get_all_tracks_in_playlist(&mut self, playlist_name: &str) -> Result<Vec<Track>, String> {
+        let songs = self.client.playlist(playlist_name.trim()).map_err(|e| e.to_string())?;
+        Ok(songs.into_iter().map(track_from_song).collect())
     }
 
-    // get elapsed time of currently playing song in seconds
-    pub fn get_elapsed(& mut self) -> i64 {
-        self.client.status().unwrap().elapsed.unwrap().num_seconds()
+    fn get_library(&mut self) -> Result<Vec<Track>, String> {
+        // every song is modified after the epoch, a search without a filter is rejected
+        let mut query = Query::new();
+        query.and(Term::LastMod, "0");
+        let songs = self.client.find(&query, None::<(u32, u32)>).map_err(|e| e.to_string())?;
+        Ok(songs.into_iter().map(track_from_song).collect())
     }
 
-    // get the playlist id of the current song
-    pub fn get_current_song_id(&mut self) -> u32 {
-        self.client.status().unwrap().song.unwrap().pos
+    fn get_status(&mut self) -> Result<Status, String> {
+        let status = self.client.status().map_err(|e| e.to_string())?;
+
+        Ok(Status {
+            state: match status.state {
+                State::Play => PlaybackState::Play,
+                State::Pause => PlaybackState::Pause,
+                State::Stop => PlaybackState::Stop,
+            },
+            volume: status.volume,
+            elapsed: match status.elapsed {
+                Some(elapsed) => elapsed.as_secs() as i64,
+                None => 0,
+            },
+            queue_position: status.song.map(|place| place.pos),
+            queue_length: status.queue_len,
+            repeat: status.repeat,
+            random: status.random,
+            single: status.single,
+            consume: status.consume,
+        })
+    }
+
+    fn get_current_track(&mut self) -> Result<Option<Track>, String> {
+        let song = self.client.currentsong().map_err(|e| e.to_string())?;
+        Ok(song.map(track_from_song))
+    }
+
+    // the server reads the cover from the directory of the song, servers older than 0.21
+    // and songs without a cover answer with an error
+    fn get_cover_art(&mut self, track: &Track) -> Option<Vec<u8>> {
+        if track.is_stream() {
+            return None;
+        }
+        let song = Song { file: track.file.clone(), ..Song::default() };
+        self.client.albumart(&song).ok().filter(|image| !image.is_empty())
+    }
+
+    // looks for an enabled httpd output
+    // servers older than 0.21 do not tell the plugin of their outputs
+    fn has_stream_output(&mut self) -> Result<bool, String> {
+        let pairs = self.raw_commands(&[String::from("outputs")])?;
+        if !pairs.iter().any(|(key, _)| key == "plugin") {
+            return Err(String::from("the server does not tell its output plugins, set the stream url"));
+        }
+
+        // the fields of an output start with its id
+        let mut found = false;
+        let (mut httpd, mut enabled) = (false, false);
+        for (key, value) in pairs.iter() {
+            match key.as_str() {
+                "outputid" => {
+                    found |= httpd && enabled;
+                    httpd = false;
+                    enabled = false;
+                },
+                "plugin" => httpd = value == "httpd",
+                "outputenabled" => enabled = value == "1",
+                _ => {},
+            }
+        }
+
+        Ok(found || (httpd && enabled))
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.client.ping().is_ok()
     }
 
     // close the connection to the server
-    pub fn close_conn(& mut self) {
+    fn close_conn(& mut self) {
         match self.client.close() {
             Ok(_) => {},
             Err(_) => {println!("Connection with the server could not be closed! Shutting down regardless.")}
@@ -222,37 +295,31 @@ impl Player {
     }
 }
 
-// get a String containing the album from a song object
-pub fn get_album_from_song(song: &Song) -> String {
-    if song.tags.get("Album").is_some() {
-        let album: &String = song.tags.get("Album").unwrap();
-        album.to_owned()
-    }
-    else {
-        String::from("unknown album")
-    }
-    
-}
-
-// get a String containing the artist from a song object
-pub fn get_artist_from_song(song: &Song) -> String {
-    if song.tags.get("Artist").is_some() {
-        let artist: &String = song.tags.get("Artist").unwrap();
-        artist.to_owned()
-    }
-    else if song.tags.get("AlbumArtist").is_some() {
-        let artist: &String = song.tags.get("AlbumArtist").unwrap();
-        artist.to_owned()
-    }
-    else {
-        String::from("unknwon artist")
-    }
+// quotes an argument of a raw command
+fn quote(argument: &str) -> String {
+    format!("\"{}\"", argument.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
-// get the duration of the song in seconds
-pub fn get_duration_from_song(song: &Song) -> i64 {
-    match song.duration {
-        None => 0,
-        Some(_x) => song.duration.unwrap().num_seconds(),
+// converts a mpd song object to a backend neutral track
+pub fn track_from_song(song: Song) -> Track {
+    let mut tags: BTreeMap<String, String> = song.tags.into_iter().collect();
+    let album_artist = tags.remove("AlbumArtist");
+    let album = tags.remove("Album");
+
+    // newer servers report the duration as a tag as well, which is used as a fallback
+    let duration = match song.duration {
+        Some(duration) => Some(duration.as_secs()),
+        None => tags.get("duration").and_then(|d| d.parse::<f64>().ok()).map(|d| d as u64),
+    };
+
+    Track {
+        file: song.file,
+        name: song.name,
+        title: song.title,
+        artist: song.artist,
+        album_artist,
+        album,
+        duration,
+        tags,
     }
 }
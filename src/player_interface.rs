@@ -1,39 +1,106 @@
 use crate::util::app;
 use crate::util::stateful_list::StatefulList;
 use crate::util::stateful_selected_list::CurrentElement;
-use crate::player::Player;
-use crate::player;
-use mpd::Song;
+use crate::backend::{Backend, Track};
+use crate::stream_listener::{StreamListener, ListenState};
+use crate::stations::{self, Station};
+use crate::mpris::{MprisServer, MprisCommand};
+use crate::notifications::Notifier;
+use crate::scrobbler::{Scrobbler, Service};
+use crate::hooks::{EventHooks, PlayerEvent};
+use crate::config::Config;
+use crate::keymap::Action;
+use crate::command_line::{PromptCommand, CompletionSource};
+use crate::cli;
+use crate::columns;
+use crate::util::table::Cell;
+use crate::sorting::{self, SortKey};
+use crate::views::{View, ViewState};
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+// the favourite radio stations are shown as a pseudo playlist with this name
+const STATIONS_PLAYLIST: &str = "Radio stations";
+// source of the stations table, playlist names can not contain a slash, so no playlist is mistaken for it
+const STATIONS_SOURCE: &str = "/stations";
+
+// steps of the seek and volume keys, multiplied by the count
+const SEEK_STEP: i64 = 5; // seconds
+const VOLUME_STEP: i32 = 5; // percent
 
 pub struct PlayerInterface {
-    music_player: Player,
+    music_player: Box<dyn Backend>,
     playlist_name: String,
     playlist_length: u32,
-    track_list: Vec<String>,
-    songs_list: Vec<Song>,
-    offset: usize,
+    stations_index: Option<usize>, // position of the stations in the sidebar, None if there are none
+    songs_list: Vec<Track>,
+    rows: Vec<usize>, // indices into songs_list in the order of the main table, after sort and filter
+    stream_url: String, // url of the httpd output of the server
+    find_stream_output: bool, // false if the url was configured, then the server is not asked for the output
+    stream_listener: Option<StreamListener>, // set while listening to the stream locally
+    mpris: Option<MprisServer>, // remote control over D-Bus, None if disabled or unavailable
+    notifier: Option<Notifier>, // desktop notifications on song change, None if disabled
+    scrobbler: Option<Scrobbler>, // submits the listening history, None if no service is configured
+    event_hooks: EventHooks, // user commands that run on player events
+    view_states: HashMap<View, ViewState>, // content of the hidden views
 }
 
 impl PlayerInterface {
-    pub fn new(ip_with_port: &String) -> PlayerInterface {
+    pub fn new(music_player: Box<dyn Backend>, stream_url: String, find_stream_output: bool) -> PlayerInterface {
         PlayerInterface {
-            music_player: Player::new(ip_with_port),
+            music_player,
             playlist_name: "".to_string(),
             playlist_length: 0,
-            track_list: Vec::<String>::new(),
-            songs_list: Vec::<Song>::new(),
-            offset: 0,
+            stations_index: None,
+            songs_list: Vec::<Track>::new(),
+            rows: Vec::new(),
+            stream_url,
+            find_stream_output,
+            stream_listener: None,
+            mpris: None,
+            notifier: None,
+            scrobbler: None,
+            event_hooks: EventHooks::new(Vec::new()),
+            view_states: HashMap::new(),
         }
     }
 
-    // Displays the playlists stored in the mpd server
+    // makes the player controllable by desktop environments and media keys
+    pub fn enable_mpris (&mut self) {
+        self.mpris = MprisServer::start();
+    }
+
+    // Displays the playlists stored in the mpd server, or the artists while browsing
     // Should be called once at application startup
     pub fn initialize (&mut self, app : &mut app::App) {
-        let playlist_list = self.music_player.get_all_playlist_names();
+        self.stations_index = None;
+        let playlist_list = if app.current_view == View::Browse {
+            self.artist_names(app)
+        }
+        else {
+            let mut names = report(self.music_player.get_all_playlist_names(), app);
+
+            // the favourite stations are shown as an additional playlist
+            if !stations::load_stations().is_empty() {
+                self.stations_index = Some(names.len());
+                names.push(String::from(STATIONS_PLAYLIST));
+            }
+            names
+        };
+
         let playlist_stateful_list = StatefulList::with_items(playlist_list);
         app.playlist_list.change_elements(playlist_stateful_list);
     }
 
+    // all artists of the library, sorted like the table
+    fn artist_names (&mut self, app : &mut app::App) -> Vec<String> {
+        let mut artists: Vec<String> = report(self.music_player.get_library(), app).iter().map(|track| track.get_artist()).collect();
+        artists.sort_by(|a, b| sorting::collate(a, b));
+        artists.dedup();
+        artists
+    }
+
     // cleanup connection
     pub  fn quit (&mut self) {
         self.music_player.close_conn();
@@ -43,53 +110,77 @@ impl PlayerInterface {
     pub fn user_action (&mut self, app : &mut app::App) {
         let current_block = &app.current_element;
 
-        // If playlist block is active, the tracks in the seleceted playlist are displayed
-        if current_block.eq(&CurrentElement::Playlists) {
-            self.playlist_name = app.playlist_list.get_selected_element().to_string();
-            self.track_list = self.music_player.get_all_titles_in_playlist(&self.playlist_name);
-            self.songs_list = self.music_player.get_all_songs_in_playlist(&self.playlist_name);
-            self.playlist_length = self.songs_list.len() as u32;
-
-            // Create vectors to store track data
-            let mut albums_vec = Vec::<String>::with_capacity(self.playlist_length as usize);
-            let mut artists_vec = Vec::<String>::with_capacity(self.playlist_length as usize);
-            let mut duration_vec = Vec::<String>::with_capacity(self.playlist_length as usize);
+        // the selected view is shown
+        if current_block.eq(&CurrentElement::Views) {
+            let view = View::from_index(app.view_list.get_selected_index());
+            self.show_view(view, app);
+        }
 
-            // Fill the vectors with values retrieved from the player
-            for i in 0..self.playlist_length as usize {
-                albums_vec.push(player::get_album_from_song(&self.songs_list[i]));
-                artists_vec.push(player::get_artist_from_song(&self.songs_list[i]));
-                duration_vec.push(PlayerInterface::transform_to_time_string(player::get_duration_from_song(&self.songs_list[i])));
+        // If playlist block is active, the tracks in the seleceted playlist are displayed
+        // while browsing the block lists the artists
+        else if current_block.eq(&CurrentElement::Playlists) {
+            let index = app.playlist_list.get_selected_index();
+            let name = if app.current_view != View::Browse && Some(index) == self.stations_index {
+                String::from(STATIONS_SOURCE)
+            }
+            else {
+                app.playlist_list.get_selected_element().to_string()
+            };
+            match app.current_view {
+                View::Browse => self.show_artist(name, app),
+                View::Playlists => self.show_playlist(name, app),
+                _ => {
+                    // the other views have no playlist, so it is opened in the playlists view
+                    self.show_view(View::Playlists, app);
+                    app.playlist_list.select(index);
+                    self.show_playlist(name, app);
+                },
             }
-
-            // Save data in StatefulList
-            let track_stateful_list = StatefulList::with_items(self.track_list.clone());
-            let albums_stateful_list = StatefulList::with_items(albums_vec);
-            let artists_stateful_list = StatefulList::with_items(artists_vec);
-            let durations_stateful_list = StatefulList::with_items(duration_vec);
-
-            // Change UI lists to display data in terminal
-            app.tracks_list.change_elements(track_stateful_list);
-            app.albums_list.change_elements(albums_stateful_list);
-            app.artist_list.change_elements(artists_stateful_list);
-            app.lengths_list.change_elements(durations_stateful_list);
         }
 
         // If main area is active, the playlist is loaded
         // into the queue and the selected song should be played
         else if current_block.eq(&CurrentElement::MainArea) {
 
-            // Get the index of the selected track
-            let selected_index = app.tracks_list.get_selected_index() as u32;
-            // ...and save it as offset for later use
-            self.offset = selected_index as usize;
+            // Get the index of the selected track in the playlist, the table can be sorted and filtered
+            let selected_index = match self.rows.get(app.table.selected()) {
+                Some(index) => *index as u32,
+                None => return,
+            };
 
-            self.music_player.clear_queue();
+            match app.current_view {
+                // the queue is already on the server
+                View::Queue => {
+                    report(self.music_player.play_position(selected_index), app);
+                    return;
+                },
+                // the shown rows are queued starting with the selected one
+                View::Library | View::Browse | View::Search => {
+                    let tracks: Vec<Track> = self.rows[app.table.selected()..].iter().map(|index| self.songs_list[*index].clone()).collect();
+                    let result = self.music_player.clear_queue()
+                        .and_then(|_| self.music_player.add_all_to_queue(&tracks))
+                        .and_then(|_| self.music_player.play());
+                    report(result, app);
+                    return;
+                },
+                _ => {},
+            }
 
-            // Load new playlist in mpd server starting with the index
-            self.music_player.load_playlist(&self.playlist_name, selected_index, self.playlist_length);
+            let result = self.music_player.clear_queue().and_then(|_| {
+                // stations are not stored on the server, so the selected one is added directly
+                if self.playlist_name == STATIONS_SOURCE {
+                    match self.songs_list.get(selected_index as usize) {
+                        Some(station) => self.music_player.add_to_queue(station),
+                        None => Ok(()),
+                    }
+                }
+                else {
+                    // Load new playlist in mpd server starting with the index
+                    self.music_player.load_playlist(&self.playlist_name, selected_index, self.playlist_length)
+                }
+            });
 
-            self.music_player.play();
+            report(result.and_then(|_| self.music_player.play()), app);
         }
 
         // If playbar controls are active, send the user action
@@ -97,49 +188,800 @@ impl PlayerInterface {
         else if current_block.eq(&CurrentElement::Playbar) {
             match app.playbar_state.index {
                 0 => {  
-                    self.music_player.prev_song();
+                    report(self.music_player.prev_song(), app);
                 },
                 1 => {
-                    self.music_player.toggle_play_pause();
+                    report(self.music_player.toggle_play_pause(), app);
                 },
                 2 => {
-                    self.music_player.next_song();
+                    report(self.music_player.next_song(), app);
                 },
                 _ => {}
             }
         }
     }
 
+    // shows the tracks of a playlist in the main area
+    fn show_playlist (&mut self, playlist_name : String, app : &mut app::App) {
+        self.playlist_name = playlist_name;
+
+        if self.playlist_name == STATIONS_SOURCE {
+            self.songs_list = stations::load_stations().iter().map(|station| station.to_track()).collect();
+        }
+        else {
+            self.songs_list = report(self.music_player.get_all_tracks_in_playlist(&self.playlist_name), app);
+        }
+        self.playlist_length = self.songs_list.len() as u32;
+
+        self.refresh_table(app);
+    }
+
+    // shows the tracks of an artist of the library while browsing
+    fn show_artist (&mut self, artist : String, app : &mut app::App) {
+        self.songs_list = report(self.music_player.get_library(), app).into_iter().filter(|track| track.get_artist() == artist.trim()).collect();
+        self.playlist_name = artist;
+        self.playlist_length = self.songs_list.len() as u32;
+
+        self.refresh_table(app);
+    }
+
+    // +---------------------------------------------------+
+    // | views, a hidden view keeps its tracks, selection, |
+    // | sort and filter until it is shown again           |
+    // +---------------------------------------------------+
+
+    pub fn show_view (&mut self, view : View, app : &mut app::App) {
+        app.view_list.select(view.index());
+
+        if view != app.current_view {
+            let state = ViewState {
+                source: mem::take(&mut self.playlist_name),
+                tracks: mem::take(&mut self.songs_list),
+                rows: mem::take(&mut self.rows),
+                table: mem::take(&mut app.table),
+                order: mem::replace(&mut app.table_order, app.default_order.clone()),
+                sidebar_index: app.playlist_list.get_selected_index(),
+            };
+            self.view_states.insert(app.current_view, state);
+            app.current_view = view;
+
+            // the sidebar lists artists while browsing and playlists in the other views
+            self.initialize(app);
+
+            match self.view_states.remove(&view) {
+                Some(state) => {
+                    self.playlist_name = state.source;
+                    self.songs_list = state.tracks;
+                    self.rows = state.rows;
+                    self.playlist_length = self.songs_list.len() as u32;
+                    app.table = state.table;
+                    app.table_order = state.order;
+                    app.playlist_list.select(state.sidebar_index);
+                },
+                None => self.load_view(app),
+            }
+        }
+
+        // these views change while they are hidden
+        match view {
+            View::Queue => {
+                let selected = app.table.selected();
+                self.songs_list = report(self.music_player.get_queue(), app);
+                self.playlist_length = self.songs_list.len() as u32;
+                self.refresh_table(app);
+                app.table.select(selected);
+            },
+            View::Search if app.table_order.filter.is_empty() => app.command_line.open('/', ""),
+            View::NowPlaying => self.update_now_playing(app),
+            View::Stats => self.update_stats(app),
+            _ => {},
+        }
+    }
+
+    // fills a view that is shown the first time
+    fn load_view (&mut self, app : &mut app::App) {
+        self.playlist_name = String::from(app.current_view.title());
+        self.songs_list = match app.current_view {
+            View::Library | View::Search => report(self.music_player.get_library(), app),
+            _ => Vec::new(),
+        };
+        self.playlist_length = self.songs_list.len() as u32;
+
+        // the albums of an artist are listed in order unless the config sorts differently
+        if app.current_view == View::Browse && app.table_order.sort.is_empty() {
+            app.table_order.sort = ["album", "disc", "track"].iter().map(|tag| SortKey { tag: tag.to_string(), descending: false }).collect();
+        }
+
+        self.refresh_table(app);
+    }
+
+    // shows the new tracks after the library changed, e.g. its first scan finished
+    pub fn check_library (&mut self, app : &mut app::App) {
+        if !self.music_player.library_changed() {
+            return;
+        }
+
+        // the hidden views that show the library are filled again when they are shown
+        self.view_states.retain(|view, _| ![View::Library, View::Browse, View::Search].contains(view));
+
+        let sidebar_index = app.playlist_list.get_selected_index();
+        let selected = app.table.selected();
+        self.initialize(app);
+        app.playlist_list.select(sidebar_index);
+
+        match app.current_view {
+            View::Playlists => self.reload_playlist(app),
+            View::Browse => {
+                let artist = self.playlist_name.clone();
+                self.show_artist(artist, app);
+            },
+            View::Library | View::Search => {
+                self.songs_list = report(self.music_player.get_library(), app);
+                self.playlist_length = self.songs_list.len() as u32;
+                self.refresh_table(app);
+            },
+            _ => return,
+        }
+        app.table.select(selected);
+    }
+
+    // tags of the current track, updated while the view is shown
+    fn update_now_playing (&mut self, app : &mut app::App) {
+        let (track, status) = match (self.music_player.get_current_track(), self.music_player.get_status()) {
+            (Ok(Some(track)), Ok(status)) => (track, status),
+            (Err(message), _) | (_, Err(message)) => {
+                app.info_lines = vec![message];
+                return;
+            },
+            _ => {
+                app.info_lines = vec![String::from("nothing is playing")];
+                return;
+            },
+        };
+
+        let mut lines = vec![
+            format!("title: {}", track.get_title()),
+            format!("artist: {}", track.get_artist()),
+            format!("album: {}", track.get_album()),
+        ];
+        if let Some(album_artist) = &track.album_artist {
+            lines.push(format!("album artist: {}", album_artist));
+        }
+        if let Some(name) = &track.name {
+            lines.push(format!("station: {}", name));
+        }
+        for (tag, value) in track.tags.iter() {
+            lines.push(format!("{}: {}", tag.to_lowercase(), value));
+        }
+
+        let length = if track.duration.is_some() { PlayerInterface::transform_to_time_string(track.get_duration()) } else { String::from("live") };
+        lines.push(format!("time: {} / {}", PlayerInterface::transform_to_time_string(status.elapsed), length));
+        if let Some(position) = status.queue_position {
+            lines.push(format!("queue: {} of {}", position + 1, status.queue_length));
+        }
+        lines.push(format!("file: {}", track.file));
+
+        app.info_lines = lines;
+    }
+
+    // numbers of the library, computed when the view is shown
+    fn update_stats (&mut self, app : &mut app::App) {
+        let library = report(self.music_player.get_library(), app);
+        let playlists = report(self.music_player.get_all_playlist_names(), app).len();
+        let queue = self.music_player.get_status().map(|status| status.queue_length).unwrap_or(0);
+
+        let artists: HashSet<String> = library.iter().map(|track| track.get_artist()).collect();
+        let albums: HashSet<(String, String)> = library.iter().map(|track| (track.get_artist(), track.get_album())).collect();
+        let length: i64 = library.iter().map(|track| track.get_duration()).sum();
+
+        app.info_lines = vec![
+            format!("playlists: {}", playlists),
+            format!("tracks: {}", library.len()),
+            format!("artists: {}", artists.len()),
+            format!("albums: {}", albums.len()),
+            format!("total length: {}", PlayerInterface::transform_to_time_string(length)),
+            format!("tracks in the queue: {}", queue),
+            String::new(),
+            String::from("CMP is made by K.Radke, L.Seyboldt & S.Stahl (c) 2020"),
+        ];
+    }
+
+    // fills the columns of the main area with the shown tracks, also called when the columns change
+    // the rows follow the sort and filter of the app
+    pub fn refresh_table (&mut self, app : &mut app::App) {
+        self.rows = app.table_order.rows(&self.songs_list, &app.columns);
+
+        // the search shows nothing until something is searched
+        if app.current_view == View::Search && app.table_order.filter.is_empty() {
+            self.rows.clear();
+        }
+
+        let rows: Vec<Vec<Cell>> = self.rows.iter().map(|i| {
+            app.columns.iter().map(|column| columns::cell(&self.songs_list[*i], column)).collect()
+        }).collect();
+        app.table.set_rows(rows);
+    }
+
+    // narrows the main table to the matching rows, the playlist on the server is not changed
+    pub fn set_filter (&mut self, filter : String, app : &mut app::App) {
+        if filter == app.table_order.filter {
+            return;
+        }
+        app.table_order.filter = filter;
+        self.refresh_table(app);
+    }
+
+    // +---------------------------------------------------+
+    // | batch operations on the marked rows of the table, |
+    // | the selected row is used if no row is marked      |
+    // +---------------------------------------------------+
+
+    // positions in the playlist of the rows the operations work on
+    fn target_positions (&self, app : &app::App) -> Vec<usize> {
+        app.table.target_rows().iter().filter_map(|row| self.rows.get(*row).cloned()).collect()
+    }
+
+    fn queue_marked (&mut self, app : &mut app::App) {
+        let positions = self.target_positions(app);
+        let tracks: Vec<Track> = positions.iter().map(|position| self.songs_list[*position].clone()).collect();
+        let result = self.music_player.add_all_to_queue(&tracks);
+
+        app.table.clear_marks();
+        app.message = match result {
+            Ok(_) => format!("queued {} tracks", positions.len()),
+            Err(message) => message,
+        };
+    }
+
+    fn add_marked_to_playlist (&mut self, playlist_name : &str, app : &mut app::App) {
+        let positions = self.target_positions(app);
+        let name = playlist_name.trim().to_string();
+        let result = positions.iter().try_for_each(|position| self.music_player.add_to_playlist(&name, &self.songs_list[*position]));
+
+        self.initialize(app); // a new playlist is shown in the sidebar
+        if app.current_view == View::Playlists && name == self.playlist_name.trim() {
+            self.reload_playlist(app);
+        }
+        app.table.clear_marks();
+        app.message = match result {
+            Ok(_) => format!("added {} tracks to {}", positions.len(), name),
+            Err(message) => message,
+        };
+    }
+
+    fn remove_marked (&mut self, app : &mut app::App) {
+        if !self.is_editable(app) {
+            return;
+        }
+
+        let positions: Vec<u32> = self.target_positions(app).into_iter().map(|p| p as u32).collect();
+        match self.music_player.remove_from_playlist(&self.playlist_name, &positions) {
+            Ok(_) => {
+                self.reload_playlist(app);
+                app.message = format!("removed {} tracks", positions.len());
+            },
+            Err(message) => app.message = message,
+        }
+    }
+
+    // moves the marked rows by count positions, they stay marked so they can be moved again
+    fn move_marked (&mut self, up : bool, count : u32, app : &mut app::App) {
+        if !self.is_editable(app) {
+            return;
+        }
+        if !app.table_order.sort.is_empty() {
+            app.message = String::from("rows can only be moved in the server order, see :sort");
+            return;
+        }
+
+        let keep_marks = app.table.marked_count() > 0;
+        let mut positions = self.target_positions(app);
+        positions.sort_unstable();
+        if positions.is_empty() {
+            return;
+        }
+
+        // the first error stops all steps, the positions are only shifted for a complete step
+        'steps: for _ in 0..count {
+            let blocked = if up { positions[0] == 0 } else { positions[positions.len() - 1] + 1 >= self.songs_list.len() };
+            if blocked {
+                break;
+            }
+
+            // the first moved track makes room for the next one
+            let order: Vec<usize> = if up { positions.clone() } else { positions.iter().rev().cloned().collect() };
+            for position in order {
+                let target = if up { position - 1 } else { position + 1 };
+                if let Err(message) = self.music_player.move_in_playlist(&self.playlist_name, position as u32, target as u32) {
+                    app.message = message;
+                    break 'steps;
+                }
+            }
+            positions = positions.iter().map(|p| if up { p - 1 } else { p + 1 }).collect();
+        }
+
+        self.reload_playlist(app);
+
+        // the selection follows the moved rows
+        let rows: Vec<usize> = positions.iter().filter_map(|p| self.rows.iter().position(|r| r == p)).collect();
+        if let Some(first) = rows.first() {
+            app.table.select(*first);
+        }
+        if keep_marks {
+            app.table.set_marks(&rows);
+        }
+    }
+
+    // stations are not stored on the server, the other views show no playlist
+    fn is_editable (&self, app : &mut app::App) -> bool {
+        if app.current_view != View::Playlists {
+            app.message = String::from("only playlists can be changed");
+            return false;
+        }
+        if self.playlist_name == STATIONS_SOURCE {
+            app.message = String::from("the stations are changed with :addstation and :rmstation");
+            return false;
+        }
+        true
+    }
+
+    // reads the shown playlist again after it was changed
+    fn reload_playlist (&mut self, app : &mut app::App) {
+        let name = self.playlist_name.clone();
+        self.show_playlist(name, app);
+    }
+
+    fn sort_table (&mut self, app : &mut app::App) {
+        self.refresh_table(app);
+        app.message = app.table_order.description();
+    }
+
+    // executes the playback actions of the keymap, the count repeats them
+    pub fn handle_action (&mut self, action : Action, count : u32, app : &mut app::App) {
+        match action {
+            Action::Select => self.user_action(app),
+            Action::TogglePlay => report(self.music_player.toggle_play_pause(), app),
+            Action::Stop => report(self.music_player.stop(), app),
+            Action::NextSong => report((0..count).try_for_each(|_| self.music_player.next_song()), app),
+            Action::PreviousSong => report((0..count).try_for_each(|_| self.music_player.prev_song()), app),
+            Action::SeekForward | Action::SeekBackward => {
+                let step = if action == Action::SeekForward { SEEK_STEP } else { -SEEK_STEP };
+                let position = self.music_player.get_elapsed() + step * count as i64;
+                report(self.music_player.seek(position.max(0)), app);
+            },
+            Action::VolumeUp | Action::VolumeDown => {
+                let volume = match self.music_player.get_status() {
+                    Ok(status) => status.volume,
+                    Err(message) => {
+                        app.message = message;
+                        return;
+                    },
+                };
+                if volume < 0 {
+                    app.message = String::from("no volume control");
+                    return;
+                }
+                let step = if action == Action::VolumeUp { VOLUME_STEP } else { -VOLUME_STEP };
+                let volume = (volume as i32 + step * count as i32).clamp(0, 100);
+                report(self.music_player.set_volume(volume as i8), app);
+            },
+            Action::ListenLocally => self.toggle_listen_locally(app),
+            Action::SortColumn => {
+                for _ in 0..count {
+                    app.table_order.next_column(&app.columns);
+                }
+                self.sort_table(app);
+            },
+            Action::ReverseSort => {
+                app.table_order.reverse();
+                self.sort_table(app);
+            },
+            Action::Queue => self.queue_marked(app),
+            Action::Remove => self.remove_marked(app),
+            Action::MoveUp | Action::MoveDown => self.move_marked(action == Action::MoveUp, count, app),
+            Action::View(index) => self.show_view(View::from_index(index), app),
+            _ => {},
+        }
+    }
+
+    // executes a command of the command line or the keymap, the result is shown as message
+    // quit is handled by the main loop, as it has to restore the terminal
+    pub fn run_command (&mut self, command : PromptCommand, app : &mut app::App) {
+        match command {
+            PromptCommand::Play => report(self.music_player.play(), app),
+            PromptCommand::Pause => report(self.music_player.pause(), app),
+            PromptCommand::Toggle => report(self.music_player.toggle_play_pause(), app),
+            PromptCommand::Stop => report(self.music_player.stop(), app),
+            PromptCommand::Next => report(self.music_player.next_song(), app),
+            PromptCommand::Previous => report(self.music_player.prev_song(), app),
+            PromptCommand::Clear => report(self.music_player.clear_queue(), app),
+            PromptCommand::Seek(position) => {
+                let elapsed = self.music_player.get_elapsed();
+                match cli::parse_relative(&position, cli::parse_time, elapsed) {
+                    Some(seconds) => report(self.music_player.seek(seconds.max(0)), app),
+                    None => app.message = format!("\"{}\" is not a time like 90, 1:30 or +10", position),
+                }
+            },
+            PromptCommand::Volume(value) => {
+                let volume = match self.music_player.get_status() {
+                    Ok(status) => status.volume,
+                    Err(message) => {
+                        app.message = message;
+                        return;
+                    },
+                };
+                let parse = |v: &str| v.parse::<i64>().ok();
+                match cli::parse_relative(&value, parse, volume as i64) {
+                    _ if volume < 0 => app.message = String::from("no volume control"),
+                    Some(v) => report(self.music_player.set_volume(v.clamp(0, 100) as i8), app),
+                    None => app.message = format!("\"{}\" is not a volume from 0 to 100", value),
+                }
+            },
+            PromptCommand::Load(name) => {
+                let names = match self.music_player.get_all_playlist_names() {
+                    Ok(names) => names,
+                    Err(message) => {
+                        app.message = message;
+                        return;
+                    },
+                };
+                match names.into_iter().find(|p| p.trim().eq_ignore_ascii_case(name.trim())) {
+                    Some(playlist) => {
+                        let result = self.music_player.get_all_tracks_in_playlist(&playlist)
+                            .and_then(|tracks| self.music_player.load_playlist(&playlist, 0, tracks.len() as u32));
+                        if let Err(message) = result {
+                            app.message = message;
+                            return;
+                        }
+
+                        // the queue shows the loaded tracks, the other views switch to the playlist
+                        if app.current_view == View::Queue {
+                            self.show_view(View::Queue, app);
+                        }
+                        else {
+                            self.show_view(View::Playlists, app);
+                            self.show_playlist(playlist, app);
+                        }
+                    },
+                    None => app.message = format!("no playlist \"{}\"", name),
+                }
+            },
+            PromptCommand::Add(tag, value) => {
+                let value = value.to_lowercase();
+                let tracks: Vec<Track> = report(self.music_player.get_library(), app).into_iter().filter(|track| {
+                    if tag == "any" {
+                        ["title", "artist", "album", "file"].iter().any(|t| track.get_tag(t).map(|v| v.to_lowercase().contains(&value)).unwrap_or(false))
+                    }
+                    else {
+                        track.get_tag(&tag).map(|v| v.to_lowercase() == value).unwrap_or(false)
+                    }
+                }).collect();
+
+                match self.music_player.add_all_to_queue(&tracks) {
+                    Ok(_) => app.message = format!("added {} tracks", tracks.len()),
+                    Err(message) => app.message = message,
+                }
+            },
+            PromptCommand::Save(name) => {
+                let length = report(self.music_player.get_queue(), app).len();
+                let result = self.music_player.save_queue(&name);
+                self.initialize(app); // shows the new playlist in the sidebar
+                if app.current_view == View::Playlists && name.trim() == self.playlist_name.trim() {
+                    self.reload_playlist(app);
+                }
+                app.message = match result {
+                    Ok(_) => format!("saved {} tracks to {}", length, name),
+                    Err(message) => message,
+                };
+            },
+            PromptCommand::Set(option, value) => {
+                let status = match self.music_player.get_status() {
+                    Ok(status) => status,
+                    Err(message) => {
+                        app.message = message;
+                        return;
+                    },
+                };
+                let result = match option.as_str() {
+                    "random" => self.music_player.set_random(value.unwrap_or(!status.random)),
+                    "repeat" => self.music_player.set_repeat(value.unwrap_or(!status.repeat)),
+                    _ => self.music_player.set_single(value.unwrap_or(!status.single)),
+                };
+                report(result, app);
+            },
+            PromptCommand::Sort(keys) => {
+                app.table_order.sort = keys;
+                self.sort_table(app);
+            },
+            PromptCommand::Filter(filter) => self.set_filter(filter, app),
+            PromptCommand::AddTo(playlist) => self.add_marked_to_playlist(&playlist, app),
+            PromptCommand::AddUrl(url, playlist) => {
+                let stream = Track { file: url.clone(), ..Track::default() };
+                match playlist {
+                    Some(name) => {
+                        let name = name.trim().to_string();
+                        let result = self.music_player.add_to_playlist(&name, &stream);
+                        self.initialize(app); // a new playlist is shown in the sidebar
+                        if app.current_view == View::Playlists && name == self.playlist_name.trim() {
+                            self.reload_playlist(app);
+                        }
+                        app.message = match result {
+                            Ok(_) => format!("added {} to {}", url, name),
+                            Err(message) => message,
+                        };
+                    },
+                    None => app.message = match self.music_player.add_to_queue(&stream) {
+                        Ok(_) => format!("added {} to the queue", url),
+                        Err(message) => message,
+                    },
+                }
+            },
+            PromptCommand::AddStation(url, name) => {
+                let mut favourites = stations::load_stations();
+                let name = name.map(|n| n.trim().to_string()).unwrap_or_else(|| url.clone());
+                if favourites.iter().any(|station| station.name == name) {
+                    app.message = format!("there is already a station named {}", name);
+                    return;
+                }
+                let message = format!("added station {}", name);
+                favourites.push(Station { name, url });
+                self.save_stations(&favourites, message, app);
+            },
+            PromptCommand::RemoveStation(name) => {
+                let mut favourites = stations::load_stations();
+                let length = favourites.len();
+                favourites.retain(|station| station.name != name && station.url != name);
+                if favourites.len() == length {
+                    app.message = format!("no station named {}", name);
+                    return;
+                }
+                self.save_stations(&favourites, format!("removed station {}", name), app);
+            },
+            PromptCommand::Quit => app.should_quit = true,
+        }
+    }
+
+    // stores the changed favourites and shows them in the sidebar
+    fn save_stations (&mut self, favourites : &[Station], message : String, app : &mut app::App) {
+        if let Err(error) = stations::save_stations(favourites) {
+            app.message = error;
+            return;
+        }
+        self.initialize(app);
+        if app.current_view == View::Playlists && self.playlist_name == STATIONS_SOURCE {
+            self.reload_playlist(app);
+        }
+        app.message = message;
+    }
+
+    // seeks to a position in the current song, e.g. clicked on the timeline
+    pub fn seek_to_ratio (&mut self, ratio : f64, app : &mut app::App) {
+        let duration = match report(self.music_player.get_current_track(), app) {
+            Some(track) => track.get_duration(),
+            None => return,
+        };
+
+        // streams and songs of unknown length can not be seeked
+        if duration > 0 {
+            report(self.music_player.seek((duration as f64 * ratio.clamp(0.0, 1.0)).round() as i64), app);
+        }
+    }
+
+    // length of the current song in seconds, none for streams and songs of unknown length
+    fn current_duration (&mut self) -> Option<i64> {
+        let track = self.music_player.get_current_track().ok()??;
+        track.duration.map(|d| d as i64)
+    }
+
+    // starts or stops playing the httpd stream of the server on this machine
+    pub fn toggle_listen_locally (&mut self, app : &mut app::App) {
+        if self.stream_listener.take().is_some() {
+            app.listen_status = String::new();
+            return;
+        }
+
+        if self.find_stream_output {
+            match self.music_player.has_stream_output() {
+                Ok(true) => {},
+                Ok(false) => {
+                    app.listen_status = String::from("no httpd output");
+                    return;
+                },
+                Err(message) => {
+                    app.message = message;
+                    return;
+                },
+            }
+        }
+
+        self.stream_listener = Some(StreamListener::start(&self.stream_url));
+    }
+
+    // applies the behaviour settings, also used when the config is reloaded
+    // MPRIS is only started once, see enable_mpris
+    pub fn apply_config (&mut self, config: &Config) {
+        self.set_notifications(config.behaviour.notify, config.behaviour.notify_unfocused);
+        self.set_scrobbling(config.scrobble_services());
+        self.event_hooks = config.event_hooks();
+    }
+
+    // shows a desktop notification whenever the song changes
+    // a reload keeps the notifier, otherwise the playing song would be notified again
+    fn set_notifications (&mut self, enabled: bool, only_unfocused: bool) {
+        if !enabled {
+            self.notifier = None;
+            return;
+        }
+        match &mut self.notifier {
+            Some(notifier) => notifier.set_only_unfocused(only_unfocused),
+            None => self.notifier = Notifier::new(only_unfocused),
+        }
+    }
+
+    // submits played songs to the given services
+    fn set_scrobbling (&mut self, services: Vec<Service>) {
+        self.scrobbler = if services.is_empty() { None } else { Some(Scrobbler::new(services)) };
+    }
+
+    // returns false if the connection to the server was lost
+    pub fn check_connection (&mut self) -> bool {
+        if self.music_player.is_connected() {
+            return true;
+        }
+
+        self.event_hooks.run(PlayerEvent::ConnectionLoss);
+        false
+    }
+
+    // runs the user commands of all events since the last call
+    pub fn run_event_hooks (&mut self) {
+        if self.event_hooks.is_empty() {
+            return;
+        }
+
+        // the hooks are run again once the server answers
+        if let (Ok(status), Ok(song)) = (self.music_player.get_status(), self.music_player.get_current_track()) {
+            self.event_hooks.update(&status, song);
+        }
+    }
+
+    // executes the requests of MPRIS clients and publishes the current state to them
+    // returns true if a client asked to quit, the main loop has to restore the terminal then
+    pub fn handle_remote_commands (&mut self, app : &mut app::App) -> bool {
+        let commands = match &self.mpris {
+            Some(server) => server.poll_commands(),
+            None => return false,
+        };
+
+        let mut quit_requested = false;
+        for command in commands {
+            match command {
+                MprisCommand::Quit => quit_requested = true,
+                command => report(self.run_remote_command(command), app),
+            }
+        }
+
+        if let (Ok(status), Ok(track), Some(server)) = (self.music_player.get_status(), self.music_player.get_current_track(), &self.mpris) {
+            server.update(&status, track);
+        }
+        quit_requested
+    }
+
+    // runs a command of a MPRIS client, quit is handled by the caller
+    fn run_remote_command (&mut self, command : MprisCommand) -> Result<(), String> {
+        match command {
+            MprisCommand::Play => self.music_player.play(),
+            MprisCommand::Pause => self.music_player.pause(),
+            MprisCommand::PlayPause => self.music_player.toggle_play_pause(),
+            MprisCommand::Stop => self.music_player.stop(),
+            MprisCommand::Next => self.music_player.next_song(),
+            MprisCommand::Previous => self.music_player.prev_song(),
+            // seeking past the end plays the next track, as the MPRIS spec requires
+            MprisCommand::Seek(offset) => {
+                let position = self.music_player.get_elapsed() + offset / 1_000_000;
+                match self.current_duration() {
+                    Some(duration) if position > duration => self.music_player.next_song(),
+                    _ => self.music_player.seek(position.max(0)),
+                }
+            },
+            // positions before the start or after the end are ignored
+            MprisCommand::SetPosition(position) => {
+                let position = position / 1_000_000;
+                match self.current_duration() {
+                    Some(duration) if position > duration => Ok(()),
+                    _ if position < 0 => Ok(()),
+                    _ => self.music_player.seek(position),
+                }
+            },
+            MprisCommand::SetVolume(volume) => {
+                self.music_player.set_volume((volume.clamp(0.0, 1.0) * 100.0).round() as i8)
+            },
+            MprisCommand::SetLoopStatus(loop_status) => {
+                self.music_player.set_repeat(loop_status != "None")
+                    .and_then(|_| self.music_player.set_single(loop_status == "Track"))
+            },
+            MprisCommand::SetShuffle(enabled) => self.music_player.set_random(enabled),
+            MprisCommand::Quit => Ok(()),
+        }
+    }
+
     // Updates the UI with playback information (Title, Artist, Playback Position)
     pub fn update_meta_display (&mut self, app: &mut app::App) {
         if self.music_player.is_playing() {
             
-            app.playbar_state.titles[1] = "‖‖";
+            app.playbar_state.titles[1] = app.theme.glyphs.pause;
+
+            // Ask the backend for the current track, the queue does not have to
+            // match the displayed playlist (e.g. it was changed by another client)
+            let song_object = match report(self.music_player.get_current_track(), app) {
+                Some(track) => track,
+                None => return,
+            };
 
-            // Get the index of the currently playing track in the queue
-            let index = self.music_player.get_current_song_id() as usize;
+            // highlight the song in the main area if it is part of the displayed playlist
+            app.playing_index = self.rows.iter().position(|i| self.songs_list[*i].file == song_object.file);
 
-            // Access the stored metadata vectors with the offset, so the
-            // data matches the currently playing track and set it in the terminal
-            app.set_track_name(self.track_list[index + self.offset].clone());
-            let song_object = self.songs_list[index + self.offset].clone();
-            app.set_artist_name(player::get_artist_from_song(&song_object));
+            if let Some(notifier) = &mut self.notifier {
+                let music_player = &mut self.music_player;
+                notifier.track_changed(&song_object, || music_player.get_cover_art(&song_object));
+            }
+
+            // streams show the live title and the name of the station
+            if song_object.is_stream() {
+                app.set_track_name(song_object.title.clone().unwrap_or(String::from("live stream")));
+                app.set_artist_name(song_object.name.clone().unwrap_or(song_object.file.clone()));
+            }
+            else {
+                app.set_track_name(song_object.get_title());
+                app.set_artist_name(song_object.get_artist());
+            }
 
             // Calculation for progress bar
-            let elapsed_seconds = self.music_player.get_elapsed();
-            let progress = 1.0 / player::get_duration_from_song(&song_object) as f64 * elapsed_seconds as f64;
-            app.current_track_progress = progress;
+            let mut elapsed_seconds = self.music_player.get_elapsed();
+
+            if let Some(scrobbler) = &mut self.scrobbler {
+                scrobbler.update(&song_object, elapsed_seconds);
+            }
+
+            // when listening to the stream, the timeline is delayed by the buffered audio
+            // so it matches what is heard
+            if let Some(listener) = &self.stream_listener {
+                elapsed_seconds = (elapsed_seconds - listener.get_latency().as_secs() as i64).max(0);
+            }
+
+            // tracks without a duration (e.g. streams) only show the elapsed time
+            let duration = song_object.get_duration();
+            if duration > 0 {
+                app.current_track_progress = (elapsed_seconds as f64 / duration as f64).clamp(0.0, 1.0);
+            }
+            else {
+                app.current_track_progress = 0.0;
+            }
             app.track_progress_text = PlayerInterface::transform_to_time_string(elapsed_seconds);
         }
         else {
-            app.playbar_state.titles[1] = ">>";
+            app.playbar_state.titles[1] = app.theme.glyphs.play;
+            app.playing_index = None;
+        }
+
+        if app.current_view == View::NowPlaying {
+            self.update_now_playing(app);
+        }
+
+        if let Some(listener) = &self.stream_listener {
+            app.listen_status = match listener.get_state() {
+                ListenState::Connecting => String::from("connecting to stream"),
+                ListenState::Buffering(percent) => format!("buffering {}%", percent),
+                ListenState::Playing => String::from("listening locally"),
+                ListenState::Error(message) => message,
+            };
         }
     }
 
     // Converts an integer value of seconds to a time string
     // format "m:ss"
-    fn transform_to_time_string(seconds_input: i64) -> String {
-        let mut seconds = seconds_input.clone();
+    pub fn transform_to_time_string(seconds_input: i64) -> String {
+        let mut seconds = seconds_input;
         let mut minutes: i64 = 0;
 
         // Add a minute for every sixty seconds
@@ -154,7 +996,208 @@ impl PlayerInterface {
             divider = ":0";
         }
         
-        let time_string = minutes.to_string() + divider + &seconds.to_string();
-        return time_string;
+        
+        minutes.to_string() + divider + &seconds.to_string()
+    }
+}
+
+// the value of a backend call, an error is shown in the message line and replaced by an empty value
+fn report<T: Default> (result : Result<T, String>, app : &mut app::App) -> T {
+    match result {
+        Ok(value) => value,
+        Err(message) => {
+            app.message = message;
+            T::default()
+        },
+    }
+}
+
+// values for the completion of the command line
+impl CompletionSource for PlayerInterface {
+    fn playlist_names(&mut self) -> Vec<String> {
+        // nothing is completed if the server does not answer
+        self.music_player.get_all_playlist_names().unwrap_or_default().iter().map(|name| name.trim().to_string()).collect()
+    }
+
+    fn tag_values(&mut self, tag: &str) -> Vec<String> {
+        let tags: Vec<&str> = if tag == "any" { vec!["title", "artist", "album"] } else { vec![tag] };
+
+        let mut values: Vec<String> = Vec::new();
+        for track in self.music_player.get_library().unwrap_or_default() {
+            for tag in tags.iter() {
+                if let Some(value) = track.get_tag(tag) {
+                    values.push(value);
+                }
+            }
+        }
+        values.sort();
+        values.dedup();
+
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PlaybackState;
+    use crate::fake_mpd::{self, FakeMpd};
+    use crate::memory_player::demo_playlists;
+    use crate::command_line;
+
+    // a player interface connected to a new fake server with the demo playlists
+    fn connect<'a>() -> (FakeMpd, PlayerInterface, app::App<'a>) {
+        let (server, player) = fake_mpd::connect_fake();
+
+        let mut player_interface = PlayerInterface::new(Box::new(player), String::new(), true);
+        let mut app = app::App::new();
+        player_interface.initialize(&mut app);
+        (server, player_interface, app)
+    }
+
+    #[test]
+    fn selecting_a_track_plays_the_playlist_from_there() {
+        let (server, mut player_interface, mut app) = connect();
+
+        // the first playlist is shown, then its second track is selected
+        player_interface.user_action(&mut app);
+        app.current_element = CurrentElement::MainArea;
+        app.table.select(1);
+        player_interface.user_action(&mut app);
+
+        let commands = server.received_commands();
+        assert!(commands.contains(&String::from("clear")));
+        assert!(commands.iter().any(|command| command.starts_with("load") && command.contains("Evening") && command.contains("1:3")));
+
+        let state = server.state();
+        let queue: Vec<String> = state.queue.iter().map(|track| track.get_title()).collect();
+        assert_eq!(queue, vec!["Lantern", "Night Ferry"]);
+        assert_eq!(state.state, PlaybackState::Play);
+        assert_eq!(state.current, Some(0));
+    }
+
+    #[test]
+    fn playing_track_is_shown() {
+        let (server, mut player_interface, mut app) = connect();
+        {
+            let mut state = server.state();
+            state.queue = demo_playlists()[0].1.clone();
+            state.current = Some(2);
+            state.state = PlaybackState::Play;
+            state.elapsed = 30.0;
+        }
+
+        player_interface.user_action(&mut app);
+        player_interface.update_meta_display(&mut app);
+
+        assert_eq!(app.track_name_list.get_elements().items.first().map(|name| name.trim()), Some("Night Ferry"));
+        assert_eq!(app.artist_name_list.get_elements().items.first().map(|name| name.trim()), Some("Mira Kell"));
+        assert_eq!(app.playing_index, Some(2));
+        assert!(app.current_track_progress > 0.0);
+    }
+
+    #[test]
+    fn listening_needs_a_httpd_output() {
+        let (_server, mut player_interface, mut app) = connect();
+
+        // the fake server only has a null output
+        player_interface.toggle_listen_locally(&mut app);
+        assert_eq!(app.listen_status, "no httpd output");
+        assert!(player_interface.stream_listener.is_none());
+    }
+
+    #[test]
+    fn stream_urls_are_added_to_the_queue_and_playlists() {
+        let (server, mut player_interface, mut app) = connect();
+
+        let url = "https://radio.example.com/live.mp3";
+        player_interface.run_command(command_line::parse(&format!("addurl {}", url)).unwrap(), &mut app);
+        player_interface.run_command(command_line::parse(&format!("addurl {} Evening", url)).unwrap(), &mut app);
+
+        let state = server.state();
+        assert_eq!(state.queue.last().map(|track| track.file.as_str()), Some(url));
+        assert_eq!(state.playlists[0].1.last().map(|track| track.file.as_str()), Some(url));
+        assert_eq!(app.message, format!("added {} to Evening", url));
+        assert!(command_line::parse("addurl file.mp3").is_err());
+        assert_eq!(command_line::parse(&format!("addstation {} Late Night", url)), Ok(PromptCommand::AddStation(url.to_string(), Some(String::from("Late Night")))));
+        assert!(command_line::parse("addstation file.mp3").is_err());
+    }
+
+    #[test]
+    fn library_is_read_from_the_server() {
+        let (server, mut player_interface, mut app) = connect();
+
+        let library = report(player_interface.music_player.get_library(), &mut app);
+        assert!(!library.is_empty());
+        assert_eq!(library.len(), server.state().library.len());
+        assert_eq!(app.message, "");
+    }
+
+    #[test]
+    fn saving_the_queue_replaces_the_playlist() {
+        let (server, mut player_interface, mut app) = connect();
+
+        let artist = server.state().library[0].artist.clone().unwrap();
+        player_interface.run_command(command_line::parse(&format!("add artist {}", artist)).unwrap(), &mut app);
+        let queue = server.state().queue.clone();
+        assert!(!queue.is_empty());
+
+        // the second save must not append the tracks again
+        player_interface.run_command(PromptCommand::Save(String::from("Saved")), &mut app);
+        player_interface.run_command(PromptCommand::Save(String::from("Saved")), &mut app);
+        assert_eq!(app.message, format!("saved {} tracks to Saved", queue.len()));
+
+        let state = server.state();
+        let saved = state.playlists.iter().find(|(name, _)| name == "Saved").map(|(_, tracks)| tracks.clone());
+        assert_eq!(saved.map(|tracks| tracks.len()), Some(queue.len()));
+    }
+
+    #[test]
+    fn remote_seeks_stay_inside_the_song() {
+        let (server, mut player_interface, _app) = connect();
+
+        let playlist = demo_playlists().remove(0).0;
+        player_interface.music_player.load_playlist(&playlist, 0, 2).unwrap();
+        player_interface.music_player.play().unwrap();
+        let duration = server.state().queue[0].duration.unwrap() as i64;
+        server.state().elapsed = 30.0;
+
+        // negative positions and positions after the end are ignored
+        player_interface.run_remote_command(MprisCommand::SetPosition(-5_000_000)).unwrap();
+        player_interface.run_remote_command(MprisCommand::SetPosition((duration + 10) * 1_000_000)).unwrap();
+        assert_eq!(server.state().elapsed, 30.0);
+        player_interface.run_remote_command(MprisCommand::SetPosition(10_000_000)).unwrap();
+        assert_eq!(server.state().elapsed, 10.0);
+
+        // seeking past the end plays the next song
+        player_interface.run_remote_command(MprisCommand::Seek(duration * 1_000_000)).unwrap();
+        assert_eq!(server.state().current, Some(1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn loading_from_the_queue_stays_in_the_queue() {
+        let (server, mut player_interface, mut app) = connect();
+
+        player_interface.show_view(View::Queue, &mut app);
+        let playlist = demo_playlists().remove(0).0;
+        player_interface.run_command(PromptCommand::Load(playlist.clone()), &mut app);
+        assert!(app.current_view == View::Queue);
+        assert_eq!(player_interface.songs_list.len(), server.state().queue.len());
+
+        // other views show the loaded playlist
+        player_interface.show_view(View::Library, &mut app);
+        player_interface.run_command(PromptCommand::Load(playlist.clone()), &mut app);
+        assert!(app.current_view == View::Playlists);
+        assert_eq!(player_interface.playlist_name, playlist);
+    }
+
+    #[test]
+    fn server_errors_are_shown_as_message() {
+        let (server, mut player_interface, mut app) = connect();
+
+        // the queue is empty, so the server refuses to play
+        player_interface.run_command(PromptCommand::Play, &mut app);
+        assert!(app.message.contains("No songs in queue"), "{}", app.message);
+        assert_eq!(server.state().state, PlaybackState::Stop);
+    }
+}
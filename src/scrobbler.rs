@@ -0,0 +1,443 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::backend::Track;
+use crate::library::{escape, unescape};
+use crate::util::paths::cache_dir;
+
+pub const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+pub const LASTFM_URL: &str = "https://ws.audioscrobbler.com";
+
+// tracks shorter than this are never scrobbled
+const MIN_TRACK_LENGTH: u64 = 30;
+
+// a track is scrobbled after half of it or this many seconds have been heard
+const MAX_LISTEN_TIME: u64 = 240;
+
+// queued scrobbles are sent again after this time
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the elapsed time is given in whole seconds, so it may advance this much more than the real time
+const ELAPSED_TOLERANCE: u64 = 2;
+
+// a server that receives scrobbles
+#[derive(Clone)]
+pub enum Service {
+    // also used for self-hosted servers with a compatible api, e.g. Maloja
+    ListenBrainz {
+        url: String,
+        token: String,
+    },
+    // the session key has to be created beforehand with the auth api
+    LastFm {
+        url: String,
+        api_key: String,
+        api_secret: String,
+        session_key: String,
+    },
+}
+
+impl Service {
+    // name used to assign queued scrobbles to a service
+    fn name(&self) -> String {
+        match self {
+            Service::ListenBrainz { url, .. } => format!("listenbrainz {}", url),
+            Service::LastFm { url, .. } => format!("lastfm {}", url),
+        }
+    }
+}
+
+// a listened track
+#[derive(Clone, PartialEq, Debug)]
+struct Listen {
+    timestamp: u64, // start of playback in seconds since the epoch
+    artist: String,
+    title: String,
+    album: Option<String>,
+    duration: Option<u64>,
+}
+
+#[derive(PartialEq, Debug)]
+enum Submission {
+    NowPlaying(Listen),
+    Scrobble(Listen),
+}
+
+// result of sending a submission
+enum Outcome {
+    Accepted,
+    Rejected, // the server will never accept it, so it is dropped
+    Failed, // the server could not be reached, try again later
+}
+
+// decides when a track is scrobbled and hands submissions to a background thread
+pub struct Scrobbler {
+    sender: Sender<Submission>,
+    current: Option<Listen>,
+    listened: u64, // seconds the current track has been heard
+    last_elapsed: i64,
+    last_update: Option<Instant>,
+    scrobbled: bool,
+}
+
+impl Scrobbler {
+    pub fn new(services: Vec<Service>) -> Scrobbler {
+        let (tx, rx) = channel();
+        thread::spawn(move || submit_loop(services, rx));
+
+        Scrobbler {
+            sender: tx,
+            current: None,
+            listened: 0,
+            last_elapsed: 0,
+            last_update: None,
+            scrobbled: false,
+        }
+    }
+
+    // should be called with the playing track and its elapsed time on every update
+    pub fn update(&mut self, track: &Track, elapsed: i64) {
+        let now = Instant::now();
+        let real_time = self.last_update.map(|last| now.duration_since(last)).unwrap_or_default();
+        self.last_update = Some(now);
+
+        for submission in self.progress(track, elapsed, real_time) {
+            let _ = self.sender.send(submission);
+        }
+    }
+
+    // decides what is submitted, real_time is the time since the previous update
+    fn progress(&mut self, track: &Track, elapsed: i64, real_time: Duration) -> Vec<Submission> {
+        let mut submissions = Vec::new();
+
+        // tracks without an artist can not be identified by the servers
+        let artist = match &track.artist {
+            Some(a) => a.clone(),
+            None => return submissions,
+        };
+
+        let listen = Listen {
+            timestamp: now(),
+            artist,
+            title: track.get_title(),
+            album: track.album.clone(),
+            duration: track.duration,
+        };
+
+        let changed = match &self.current {
+            Some(current) => current.artist != listen.artist || current.title != listen.title || elapsed < self.last_elapsed - 2,
+            None => true,
+        };
+
+        if changed {
+            submissions.push(Submission::NowPlaying(listen.clone()));
+            self.current = Some(listen);
+            self.listened = 0;
+            self.scrobbled = false;
+        }
+        // seeking forward does not count as listening, the time can only advance as fast as the
+        // real time, which depends on the tick rate
+        else if elapsed > self.last_elapsed && (elapsed - self.last_elapsed) as u64 <= real_time.as_secs() + ELAPSED_TOLERANCE {
+            self.listened += (elapsed - self.last_elapsed) as u64;
+        }
+        self.last_elapsed = elapsed;
+
+        let current = match &self.current {
+            Some(c) => c,
+            None => return submissions,
+        };
+        let duration = match current.duration {
+            Some(d) if d >= MIN_TRACK_LENGTH => d,
+            _ => return submissions,
+        };
+
+        if !self.scrobbled && self.listened >= (duration / 2).min(MAX_LISTEN_TIME) {
+            self.scrobbled = true;
+            submissions.push(Submission::Scrobble(current.clone()));
+        }
+        submissions
+    }
+}
+
+// sends the submissions, failed scrobbles are queued on disk and retried
+fn submit_loop(services: Vec<Service>, rx: Receiver<Submission>) {
+    let mut queue = load_queue();
+
+    loop {
+        match rx.recv_timeout(RETRY_INTERVAL) {
+            // now playing is only useful right now, so it is not queued
+            Ok(Submission::NowPlaying(listen)) => {
+                for service in services.iter() {
+                    submit(service, &listen, true);
+                }
+            },
+            Ok(Submission::Scrobble(listen)) => {
+                for service in services.iter() {
+                    queue.push((service.name(), listen.clone()));
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if !queue.is_empty() {
+            flush_queue(&services, &mut queue);
+            save_queue(&queue);
+        }
+    }
+}
+
+// sends queued scrobbles in order, a service is skipped after its first failure
+fn flush_queue(services: &[Service], queue: &mut Vec<(String, Listen)>) {
+    let mut unreachable: Vec<String> = Vec::new();
+
+    queue.retain(|(name, listen)| {
+        if unreachable.contains(name) {
+            return true;
+        }
+
+        // scrobbles of services that are not configured anymore are kept for later
+        let service = match services.iter().find(|s| &s.name() == name) {
+            Some(s) => s,
+            None => return true,
+        };
+
+        match submit(service, listen, false) {
+            Outcome::Accepted | Outcome::Rejected => false,
+            Outcome::Failed => {
+                unreachable.push(name.clone());
+                true
+            },
+        }
+    });
+}
+
+fn submit(service: &Service, listen: &Listen, now_playing: bool) -> Outcome {
+    let result = match service {
+        Service::ListenBrainz { url, token } => {
+            let mut track_metadata = json!({
+                "artist_name": listen.artist,
+                "track_name": listen.title,
+            });
+            if let Some(album) = &listen.album {
+                track_metadata["release_name"] = json!(album);
+            }
+            if let Some(duration) = listen.duration {
+                track_metadata["additional_info"] = json!({ "duration": duration });
+            }
+
+            let body = if now_playing {
+                json!({
+                    "listen_type": "playing_now",
+                    "payload": [{ "track_metadata": track_metadata }],
+                })
+            }
+            else {
+                json!({
+                    "listen_type": "single",
+                    "payload": [{ "listened_at": listen.timestamp, "track_metadata": track_metadata }],
+                })
+            };
+
+            ureq::post(&format!("{}/1/submit-listens", url.trim_end_matches('/')))
+                .timeout(REQUEST_TIMEOUT)
+                .set("Authorization", &format!("Token {}", token))
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())
+        },
+        Service::LastFm { url, api_key, api_secret, session_key } => {
+            let mut params: Vec<(&str, String)> = vec![
+                ("method", String::from(if now_playing { "track.updateNowPlaying" } else { "track.scrobble" })),
+                ("artist", listen.artist.clone()),
+                ("track", listen.title.clone()),
+                ("api_key", api_key.clone()),
+                ("sk", session_key.clone()),
+            ];
+            if let Some(album) = &listen.album {
+                params.push(("album", album.clone()));
+            }
+            if let Some(duration) = listen.duration {
+                params.push(("duration", duration.to_string()));
+            }
+            if !now_playing {
+                params.push(("timestamp", listen.timestamp.to_string()));
+            }
+
+            // the signature is the md5 of all parameters sorted by name followed by the secret
+            params.sort();
+            let mut signature = String::new();
+            for (key, value) in params.iter() {
+                signature.push_str(key);
+                signature.push_str(value);
+            }
+            signature.push_str(api_secret);
+            params.push(("api_sig", format!("{:x}", md5::compute(signature))));
+            params.push(("format", String::from("json")));
+
+            let form: Vec<(&str, &str)> = params.iter().map(|(key, value)| (*key, value.as_str())).collect();
+            ureq::post(&format!("{}/2.0/", url.trim_end_matches('/')))
+                .timeout(REQUEST_TIMEOUT)
+                .send_form(&form)
+        },
+    };
+
+    match result {
+        Ok(_) => Outcome::Accepted,
+        // rate limits and server errors are temporary
+        Err(ureq::Error::Status(code, _)) if code == 429 || code >= 500 => Outcome::Failed,
+        Err(ureq::Error::Status(_, _)) => Outcome::Rejected,
+        Err(_) => Outcome::Failed,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// +--------------------------------------------------------------+
+// | queue format: one scrobble per line, fields separated by tabs |
+// | service, timestamp, artist, title, album, duration            |
+// +--------------------------------------------------------------+
+
+fn queue_file() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("scrobbles.queue"))
+}
+
+fn load_queue() -> Vec<(String, Listen)> {
+    let content = match queue_file().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(c) => c,
+        None => return Vec::new(),
+    };
+
+    content.lines().filter_map(|line| {
+        let fields: Vec<String> = line.split('\t').map(unescape).collect();
+        if fields.len() != 6 {
+            return None;
+        }
+
+        Some((fields[0].clone(), Listen {
+            timestamp: fields[1].parse::<u64>().ok()?,
+            artist: fields[2].clone(),
+            title: fields[3].clone(),
+            album: if fields[4].is_empty() { None } else { Some(fields[4].clone()) },
+            duration: fields[5].parse::<u64>().ok(),
+        }))
+    }).collect()
+}
+
+// errors are ignored, the scrobbles are only lost if the player is closed while offline
+fn save_queue(queue: &[(String, Listen)]) {
+    let path = match queue_file() {
+        Some(p) => p,
+        None => return,
+    };
+
+    let mut content = String::new();
+    for (name, listen) in queue.iter() {
+        let fields = [
+            escape(name),
+            listen.timestamp.to_string(),
+            escape(&listen.artist),
+            escape(&listen.title),
+            escape(listen.album.as_deref().unwrap_or("")),
+            listen.duration.map(|d| d.to_string()).unwrap_or_default(),
+        ];
+        content.push_str(&fields.join("\t"));
+        content.push('\n');
+    }
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = fs::File::create(path) {
+        let _ = file.write_all(content.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_secs(1);
+
+    // a scrobbler without a submit thread, the submissions are checked directly
+    fn scrobbler() -> Scrobbler {
+        let (sender, _) = channel();
+        Scrobbler { sender, current: None, listened: 0, last_elapsed: 0, last_update: None, scrobbled: false }
+    }
+
+    fn song(title: &str, duration: u64) -> Track {
+        Track {
+            file: format!("{}.mp3", title),
+            title: Some(title.to_string()),
+            artist: Some(String::from("artist")),
+            duration: Some(duration),
+            ..Track::default()
+        }
+    }
+
+    // plays the track from start to end and returns the elapsed times at which it was scrobbled
+    fn play(scrobbler: &mut Scrobbler, track: &Track, start: i64, end: i64, step: i64, real_time: Duration) -> Vec<i64> {
+        let mut scrobbled = Vec::new();
+        for elapsed in (start..=end).step_by(step as usize) {
+            let submissions = scrobbler.progress(track, elapsed, real_time);
+            if submissions.iter().any(|s| matches!(s, Submission::Scrobble(_))) {
+                scrobbled.push(elapsed);
+            }
+        }
+        scrobbled
+    }
+
+    #[test]
+    fn tracks_are_scrobbled_after_half_or_four_minutes() {
+        let mut s = scrobbler();
+        assert_eq!(play(&mut s, &song("short", 100), 0, 100, 1, TICK), vec![50]);
+
+        let mut s = scrobbler();
+        assert_eq!(play(&mut s, &song("long", 1000), 0, 1000, 1, TICK), vec![240]);
+
+        let mut s = scrobbler();
+        assert!(play(&mut s, &song("tiny", 20), 0, 20, 1, TICK).is_empty());
+    }
+
+    #[test]
+    fn slow_ticks_still_count_as_listening() {
+        let mut s = scrobbler();
+        assert_eq!(play(&mut s, &song("song", 100), 0, 100, 5, Duration::from_secs(5)), vec![50]);
+    }
+
+    #[test]
+    fn seeking_forward_is_not_listening() {
+        let mut s = scrobbler();
+        let track = song("song", 100);
+        assert!(play(&mut s, &track, 0, 10, 1, TICK).is_empty());
+        assert!(play(&mut s, &track, 80, 100, 1, TICK).is_empty());
+        assert_eq!(s.listened, 30);
+    }
+
+    #[test]
+    fn a_new_track_or_a_restart_is_a_new_listen() {
+        let mut s = scrobbler();
+        let track = song("song", 100);
+        assert_eq!(s.progress(&track, 0, TICK).len(), 1);
+        assert_eq!(play(&mut s, &track, 1, 60, 1, TICK), vec![50]);
+
+        // seeking back a little is no restart
+        assert!(s.progress(&track, 59, TICK).is_empty());
+
+        // playing the track again from the start scrobbles it again
+        assert!(matches!(s.progress(&track, 0, TICK)[..], [Submission::NowPlaying(_)]));
+        assert_eq!(play(&mut s, &track, 1, 60, 1, TICK), vec![50]);
+
+        assert!(matches!(s.progress(&song("other", 100), 0, TICK)[..], [Submission::NowPlaying(_)]));
+        assert_eq!(s.listened, 0);
+    }
+}
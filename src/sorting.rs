@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::env;
+use std::sync::OnceLock;
+
+use icu_collator::options::{CollatorOptions, Strength};
+use icu_collator::preferences::CollationNumericOrdering;
+use icu_collator::{CollatorBorrowed, CollatorPreferences};
+use icu_locale_core::Locale;
+
+use crate::backend::Track;
+use crate::columns::{self, COLUMN_TAGS};
+use crate::config::ColumnConfig;
+
+// how the '/' filter matches the rows
+pub const FILTER_MODES: [&str; 2] = ["substring", "fuzzy"];
+
+// a key of the multi-key sort, written as "album" or "-date" for descending
+#[derive(Clone, PartialEq, Debug)]
+pub struct SortKey {
+    pub tag: String,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn parse(value: &str) -> Result<SortKey, String> {
+        let descending = value.starts_with('-');
+        let tag = value.trim_start_matches(['-', '+']).to_lowercase();
+        if !COLUMN_TAGS.contains(&tag.as_str()) {
+            return Err(format!("unknown tag \"{}\" (available: {})", tag, COLUMN_TAGS.join(", ")));
+        }
+
+        Ok(SortKey { tag, descending })
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}{}", if self.descending { "-" } else { "" }, self.tag)
+    }
+}
+
+// how the tracks of the main table are arranged, the playlist on the server is not changed
+#[derive(Clone, Default, Debug)]
+pub struct TableOrder {
+    pub sort: Vec<SortKey>, // empty keeps the order of the server
+    pub ignore_the: bool, // "The Beatles" is sorted as "Beatles"
+    pub filter: String, // rows have to match it in one of their columns
+    pub fuzzy: bool, // the characters of the filter only have to appear in order
+}
+
+impl TableOrder {
+    // indices of the tracks that are shown, in the order they are shown
+    pub fn rows(&self, tracks: &[Track], columns: &[ColumnConfig]) -> Vec<usize> {
+        let filter = accent_insensitive(&self.filter);
+        let mut rows: Vec<usize> = (0..tracks.len()).filter(|i| {
+            filter.is_empty() || columns.iter().any(|column| self.matches(&accent_insensitive(&columns::cell(&tracks[*i], column).text()), &filter))
+        }).collect();
+
+        // sort_by is stable, so equal tracks keep the order of the server
+        rows.sort_by(|a, b| self.compare(&tracks[*a], &tracks[*b]));
+        rows
+    }
+
+    fn matches(&self, text: &str, filter: &str) -> bool {
+        if self.fuzzy {
+            let mut chars = text.chars();
+            filter.chars().all(|f| chars.any(|c| c == f))
+        }
+        else {
+            text.contains(filter)
+        }
+    }
+
+    fn compare(&self, a: &Track, b: &Track) -> Ordering {
+        for key in self.sort.iter() {
+            let ordering = match key.tag.as_str() {
+                "length" => a.get_duration().cmp(&b.get_duration()),
+                tag => compare_values(&self.sort_value(a, tag), &self.sort_value(b, tag)),
+            };
+            let ordering = if key.descending { ordering.reverse() } else { ordering };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    }
+
+    fn sort_value(&self, track: &Track, tag: &str) -> String {
+        let value = columns::tag_value(track, tag).unwrap_or_default().trim().to_string();
+        let article = value.get(..4).map(|start| start.eq_ignore_ascii_case("the ")).unwrap_or(false);
+        if self.ignore_the && article && value.len() > 4 {
+            value[4..].trim_start().to_string()
+        }
+        else {
+            value
+        }
+    }
+
+    // the sort after the next column of the table: each column ascending, then descending, then the server order
+    pub fn next_column(&mut self, columns: &[ColumnConfig]) {
+        let tags: Vec<String> = columns.iter().filter_map(|c| c.tag.clone()).map(|t| t.to_lowercase()).collect();
+        let current = self.sort.first().and_then(|key| tags.iter().position(|t| *t == key.tag).map(|i| (i, key.descending)));
+
+        let next = match current {
+            None => Some((0, false)),
+            Some((i, false)) => Some((i, true)),
+            Some((i, true)) if i + 1 < tags.len() => Some((i + 1, false)),
+            Some(_) => None,
+        };
+
+        self.sort = match next {
+            Some((i, descending)) if i < tags.len() => vec![SortKey { tag: tags[i].clone(), descending }],
+            _ => Vec::new(),
+        };
+    }
+
+    pub fn reverse(&mut self) {
+        for key in self.sort.iter_mut() {
+            key.descending = !key.descending;
+        }
+    }
+
+    // e.g. "sorted by album, disc, -track", shown as message
+    pub fn description(&self) -> String {
+        if self.sort.is_empty() {
+            return String::from("server order");
+        }
+        format!("sorted by {}", self.sort.iter().map(|k| k.name()).collect::<Vec<String>>().join(", "))
+    }
+}
+
+// compares by the collation of the user's locale, empty values are sorted to the end
+fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => collate(a, b),
+    }
+}
+
+// compares two texts like a dictionary of the user's locale (LC_ALL, LC_COLLATE or LANG)
+// case is ignored and numbers are compared by value, so "9" comes before "10"
+pub fn collate(a: &str, b: &str) -> Ordering {
+    static COLLATOR: OnceLock<Option<CollatorBorrowed<'static>>> = OnceLock::new();
+
+    let collator = COLLATOR.get_or_init(|| {
+        let mut preferences = CollatorPreferences::from(&collation_locale());
+        preferences.numeric_ordering = Some(CollationNumericOrdering::True);
+        let mut options = CollatorOptions::default();
+        options.strength = Some(Strength::Secondary);
+        CollatorBorrowed::try_new(preferences, options).ok()
+    });
+
+    match collator {
+        Some(collator) => collator.compare(a, b),
+        None => accent_insensitive(a).cmp(&accent_insensitive(b)),
+    }
+}
+
+// the locale of the environment, e.g. "de_DE.UTF-8" is read as "de-DE"
+// the root collation is used if none is set
+fn collation_locale() -> Locale {
+    let value = ["LC_ALL", "LC_COLLATE", "LANG"].iter()
+        .filter_map(|variable| env::var(variable).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let name = value.split(['.', '@']).next().unwrap_or("").replace('_', "-");
+
+    match name.as_str() {
+        "" | "C" | "POSIX" => Locale::UNKNOWN,
+        name => Locale::try_from_str(name).unwrap_or(Locale::UNKNOWN),
+    }
+}
+
+// lowercase without accents, so "Émile" is found like "emile" by the filter
+pub fn accent_insensitive(value: &str) -> String {
+    value.trim().to_lowercase().chars().map(|c| match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => 'i',
+        'ł' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' | 'ţ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        c => c,
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_collated() {
+        // accents sort next to their base letter, case is ignored and numbers by value
+        let mut values = vec!["zebra", "Émile", "apple", "eagle", "Ángel", "10", "9", ""];
+        values.sort_by(|a, b| compare_values(a, b));
+        assert_eq!(values, vec!["9", "10", "Ángel", "apple", "eagle", "Émile", "zebra", ""]);
+    }
+
+    #[test]
+    fn leading_article_is_ignored() {
+        let order = TableOrder { sort: vec![SortKey::parse("artist").unwrap()], ignore_the: true, ..TableOrder::default() };
+        let track = |artist: &str| Track { artist: Some(artist.to_string()), ..Track::default() };
+        let tracks = vec![track("The Who"), track("Beatles"), track("the Doors"), track("Abba")];
+        assert_eq!(order.rows(&tracks, &[]), vec![3, 1, 2, 0]);
+    }
+}
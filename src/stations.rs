@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::backend::Track;
+use crate::util::paths::config_dir;
+
+const EXTINF: &str = "#EXTINF:-1,";
+
+// a favourite internet radio station
+#[derive(Clone, PartialEq, Debug)]
+pub struct Station {
+    pub name: String,
+    pub url: String,
+}
+
+impl Station {
+    // creates a track that plays the station
+    pub fn to_track(&self) -> Track {
+        Track {
+            file: self.url.clone(),
+            name: Some(self.name.clone()),
+            ..Track::default()
+        }
+    }
+}
+
+// returns true if the uri is a stream url instead of a file
+pub fn is_stream_url(uri: &str) -> bool {
+    let uri = uri.to_lowercase();
+    uri.starts_with("http://") || uri.starts_with("https://")
+}
+
+// the stations are stored as an extended m3u file in the config directory:
+//   #EXTINF:-1,Station Name
+//   https://example.com/stream.mp3
+fn stations_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("stations.m3u"))
+}
+
+// loads the favourite stations, a missing file means there are no stations
+pub fn load_stations() -> Vec<Station> {
+    match stations_file().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(content) => parse_stations(&content),
+        None => Vec::new(),
+    }
+}
+
+// replaces the stations in the file, used by :addstation and :rmstation
+pub fn save_stations(stations: &[Station]) -> Result<(), String> {
+    let path = stations_file().ok_or_else(|| String::from("no config directory for the stations"))?;
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    fs::write(&path, format_stations(stations)).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+fn parse_stations(content: &str) -> Vec<Station> {
+    let mut stations: Vec<Station> = Vec::new();
+    let mut name: Option<String> = None;
+
+    for line in content.lines().map(|l| l.trim()) {
+        if line.starts_with("#EXTINF:") {
+            // the name follows the first comma
+            name = line.split_once(',').map(|(_, n)| n.trim().to_string());
+        }
+        else if !line.is_empty() && !line.starts_with('#') {
+            stations.push(Station {
+                name: name.take().unwrap_or(line.to_string()),
+                url: line.to_string(),
+            });
+        }
+    }
+
+    stations
+}
+
+fn format_stations(stations: &[Station]) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for station in stations {
+        content.push_str(&format!("{}{}\n{}\n", EXTINF, station.name, station.url));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_stations_are_loaded_again() {
+        let stations = vec![
+            Station { name: String::from("Jazz, Soul & Funk"), url: String::from("https://example.com/jazz.mp3") },
+            Station { name: String::from("http://example.com/news"), url: String::from("http://example.com/news") },
+        ];
+        assert_eq!(parse_stations(&format_stations(&stations)), stations);
+    }
+}
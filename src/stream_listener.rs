@@ -0,0 +1,193 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rodio::Sink;
+use rodio::buffer::SamplesBuffer;
+
+// audio that is buffered before playback starts
+const TARGET_LATENCY_MS: u64 = 1500;
+
+// frames are dropped while more than this is buffered, so we catch up with the server
+const MAX_LATENCY_MS: u64 = 3000;
+
+// the connection is considered lost if no data arrives for this long
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ListenState {
+    Connecting,
+    Buffering(u8), // fill level of the buffer in percent
+    Playing,
+    Error(String),
+}
+
+// plays the http stream of a mpd httpd output on the local machine
+// the stream is received and decoded in a background thread that stops when this is dropped
+pub struct StreamListener {
+    state: Arc<Mutex<ListenState>>,
+    latency_ms: Arc<AtomicU64>, // duration of the audio buffered locally
+    stop: Arc<AtomicBool>,
+}
+
+impl StreamListener {
+    // connects to the stream and starts playing it
+    pub fn start(url: &str) -> StreamListener {
+        let listener = StreamListener {
+            state: Arc::new(Mutex::new(ListenState::Connecting)),
+            latency_ms: Arc::new(AtomicU64::new(0)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        let url = url.to_string();
+        let state = listener.state.clone();
+        let latency_ms = listener.latency_ms.clone();
+        let stop = listener.stop.clone();
+
+        thread::spawn(move || {
+            if let Err(message) = play_stream(&url, &state, &latency_ms, &stop) {
+                *state.lock().unwrap() = ListenState::Error(message);
+            }
+        });
+
+        listener
+    }
+
+    pub fn get_state(&self) -> ListenState {
+        self.state.lock().unwrap().clone()
+    }
+
+    // the delay between receiving audio and hearing it
+    // used to shift the timeline so it matches what is heard
+    pub fn get_latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms.load(Ordering::Relaxed))
+    }
+}
+
+impl Drop for StreamListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// receives, decodes and plays the stream until it ends or stop is set
+fn play_stream(url: &str, state: &Mutex<ListenState>, latency_ms: &AtomicU64, stop: &AtomicBool) -> Result<(), String> {
+    let device = rodio::default_output_device().ok_or(String::from("no audio output device"))?;
+    let sink = Sink::new(&device);
+
+    let body = open_http_stream(url)?;
+    let mut decoder = minimp3::Decoder::new(body);
+
+    let mut buffering = true;
+    sink.pause();
+
+    while !stop.load(Ordering::Relaxed) {
+        let frame = match decoder.next_frame() {
+            Ok(f) => f,
+            Err(minimp3::Error::Eof) => return Err(String::from("stream ended")),
+            Err(minimp3::Error::Io(e)) => return Err(format!("connection lost: {}", e)),
+            Err(_) => continue,
+        };
+
+        if frame.channels == 0 || frame.sample_rate <= 0 {
+            continue;
+        }
+
+        // buffer again if the sink ran empty because of a slow connection
+        if !buffering && sink.empty() {
+            buffering = true;
+            sink.pause();
+        }
+
+        // every queued source in the sink is one frame, so the latency is frames * frame duration
+        let frame_ms = (frame.data.len() / frame.channels) as u64 * 1000 / frame.sample_rate as u64;
+        let latency = sink.len() as u64 * frame_ms;
+
+        // drop frames if too much is buffered, e.g. because the server sent a burst on connect
+        if latency < MAX_LATENCY_MS {
+            sink.append(SamplesBuffer::new(frame.channels as u16, frame.sample_rate as u32, frame.data));
+        }
+
+        let latency = sink.len() as u64 * frame_ms;
+        latency_ms.store(latency, Ordering::Relaxed);
+
+        // wait until enough is buffered before playing
+        if buffering && latency >= TARGET_LATENCY_MS {
+            buffering = false;
+            sink.play();
+            *state.lock().unwrap() = ListenState::Playing;
+        }
+
+        if buffering {
+            let percent = (latency * 100 / TARGET_LATENCY_MS).min(100) as u8;
+            *state.lock().unwrap() = ListenState::Buffering(percent);
+        }
+    }
+
+    sink.stop();
+    Ok(())
+}
+
+// sends a GET request for the url and returns the response body
+// only plain http is supported, which is what the mpd httpd output serves
+fn open_http_stream(url: &str) -> Result<impl Read, String> {
+    let (host, path) = parse_http_url(url).ok_or(format!("invalid stream url: {}", url))?;
+
+    let mut stream = TcpStream::connect(&host).map_err(|e| format!("could not connect to {}: {}", host, e))?;
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: rust-cmp\r\nIcy-MetaData: 0\r\n\r\n", path, host)
+        .map_err(|e| format!("could not send request: {}", e))?;
+
+    let mut reader = BufReader::new(stream);
+
+    // status line, e.g. "HTTP/1.1 200 OK" or "ICY 200 OK"
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(|e| format!("no response: {}", e))?;
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("server responded with: {}", status_line.trim()));
+    }
+
+    // skip the headers, only mp3 streams can be decoded
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| format!("no response: {}", e))?;
+        let header = header.trim().to_lowercase();
+
+        if header.is_empty() {
+            break;
+        }
+        if header.starts_with("content-type:") && !header.contains("mpeg") {
+            return Err(format!("unsupported stream format ({}), only mp3 is supported", header[13..].trim()));
+        }
+    }
+
+    Ok(reader)
+}
+
+// splits "http://host:port/path" into ("host:port", "/path")
+fn parse_http_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("http://")?;
+
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+
+    if host.is_empty() {
+        return None;
+    }
+
+    // the default http port is used if none is given
+    let host = if host.ends_with(']') || !host.contains(':') {
+        format!("{}:80", host)
+    }
+    else {
+        host.to_string()
+    };
+
+    Some((host, path.to_string()))
+}
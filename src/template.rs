@@ -0,0 +1,58 @@
+// renders format templates like "[%artist% - ]%title%"
+//   %name%   is replaced with the value of the placeholder
+//   [...]    is only shown if all placeholders inside have a value, sections can be nested
+//   \x       is the literal character x, e.g. \[ or \%
+pub fn render<F: Fn(&str) -> Option<String>>(template: &str, values: &F) -> String {
+    let chars: Vec<char> = template.chars().collect();
+    let mut position = 0;
+
+    render_section(&chars, &mut position, values).0
+}
+
+// renders until the end of the template or the closing bracket of the section
+// returns the text and whether all placeholders had a value
+fn render_section<F: Fn(&str) -> Option<String>>(chars: &[char], position: &mut usize, values: &F) -> (String, bool) {
+    let mut result = String::new();
+    let mut complete = true;
+
+    while *position < chars.len() {
+        let c = chars[*position];
+        *position += 1;
+
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.get(*position) {
+                    result.push(*escaped);
+                    *position += 1;
+                }
+            },
+            '[' => {
+                let (section, section_complete) = render_section(chars, position, values);
+                if section_complete {
+                    result.push_str(&section);
+                }
+            },
+            ']' => return (result, complete),
+            '%' => {
+                // a % without a closing % is kept as it is
+                let end = match chars[*position..].iter().position(|c| *c == '%') {
+                    Some(e) => *position + e,
+                    None => {
+                        result.push('%');
+                        continue;
+                    },
+                };
+                let name: String = chars[*position..end].iter().collect();
+                *position = end + 1;
+
+                match values(&name) {
+                    Some(value) if !value.is_empty() => result.push_str(&value),
+                    _ => complete = false,
+                }
+            },
+            _ => result.push(c),
+        }
+    }
+
+    (result, complete)
+}
@@ -1,17 +1,38 @@
 use crate::util::app::App;
-use crate::util::stateful_selected_list::CurrentElement;
+use crate::util::stateful_selected_list::{CurrentElement, StatefulSelectedList};
+use crate::util::theme::Theme;
+use crate::keymap::Action;
+use crate::help;
+use crate::mouse::BlockAreas;
+use crate::columns;
+use crate::views::VIEWS;
 
 use std::io;
 use std::io::stdout;
 
+use crossterm::{ExecutableCommand, event::EnableMouseCapture};
+
 use tui::{
     Terminal, Frame,
-    widgets::{Block, Borders, List, Text, Tabs, Gauge},
-    layout::{Layout, Constraint, Direction, Rect},
-    style::{Color, Style},
+    widgets::{Block, Borders, List, Paragraph, Text, Tabs, Gauge, Table, Row},
+    layout::{Layout, Constraint, Direction, Rect, Alignment},
+    style::{Color, Modifier, Style},
     backend::{CrosstermBackend}
 };
 
+// +-------------------------------------------------------------------+
+// | breakpoints of the layout, smaller terminals get a simpler layout |
+// +-------------------------------------------------------------------+
+
+const MIN_WIDTH: u16 = 30; // below this only a message is shown
+const MIN_HEIGHT: u16 = 8;
+const SIDEBAR_MIN_WIDTH: u16 = 80; // the sidebar is only shown while it is focused
+const PLAYBAR_MIN_WIDTH: u16 = 70; // the playbar is folded into a single line (width of the main block)
+const PLAYBAR_MIN_HEIGHT: u16 = 16;
+
+// gap between the columns of the main table
+const TABLE_COLUMN_SPACING: u16 = 1;
+
 pub fn init_terminal() -> Terminal<CrosstermBackend<std::io::Stdout>> {
 
     // init basic terminal objects
@@ -25,55 +46,173 @@ pub fn init_terminal() -> Terminal<CrosstermBackend<std::io::Stdout>> {
         Err(_) => println!("Error: Cursor could not be hidden! Program is continuing regardless."),
     } 
 
+    // report clicks and the scroll wheel as events
+    match io::stdout().execute(EnableMouseCapture) {
+        Ok(_) => {},
+        Err(_) => println!("Error: mouse capture could not be enabled! Program is continuing regardless."),
+    }
+
     // clear terminal
     match terminal.clear() {
         Ok(_) => {},
         Err(_) => println!("Error: terminal could not be cleared! Program is continuing regardless."),
     } 
     
-    return terminal;
+    terminal
 }
 
 // function that initiates calls to all other sub draw functions
 pub fn draw_terminal(terminal : &mut Terminal<CrosstermBackend<std::io::Stdout>>, app : &mut App) -> io::Result<()> {
 
         terminal.draw(|mut f| {
+            // the prompt or the footer take the last line of the screen
+            let mut area = f.size();
+            if app.command_line.active && area.height > 1 {
+                area.height -= 1;
+                draw_command_line(&mut f, app, Rect::new(area.x, area.y + area.height, area.width, 1));
+            }
+            else if app.show_footer && area.height > 1 {
+                area.height -= 1;
+                draw_footer(&mut f, app, Rect::new(area.x, area.y + area.height, area.width, 1));
+            }
+
+            // the areas are set again by the blocks that are drawn
+            app.areas = BlockAreas::default();
+
+            if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+                draw_too_small(&mut f, area);
+                return;
+            }
+
+            // small terminals only show the sidebar while it is focused
+            let sidebar_focused = app.current_element == CurrentElement::Views || app.current_element == CurrentElement::Playlists;
+            let sidebar_width = if area.width >= SIDEBAR_MIN_WIDTH {
+                app.sidebar_width
+            }
+            else if sidebar_focused {
+                40
+            }
+            else {
+                0
+            };
+
             // set basic chunks
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints(
                     [
-                        Constraint::Percentage(20), // minimal sidebar length
-                        Constraint::Percentage(80), // default main window length
+                        Constraint::Percentage(sidebar_width), // minimal sidebar length
+                        Constraint::Percentage(100 - sidebar_width), // default main window length
                     ].as_ref()
                 )
-                .split(f.size());
+                .split(area);
             
             // draw basic blocks
-            draw_sidebar(&mut f, app, chunks[0]);
+            if sidebar_width > 0 {
+                draw_sidebar(&mut f, app, chunks[0]);
+            }
             draw_main_block(&mut f, app, chunks[1]);
+
+            // the help is drawn over the other blocks
+            if app.help_visible {
+                draw_help(&mut f, app, area);
+            }
             
             })
 
 }
 
+// draws the ':' prompt, the cursor is shown as an inverted character
+fn draw_command_line(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+{
+    let input = &app.command_line.input;
+    let position = input.char_indices().nth(app.command_line.cursor).map(|(i, _)| i).unwrap_or(input.len());
+    let (before, after) = input.split_at(position);
+    let mut rest = after.chars();
+    let cursor = rest.next().map(|c| c.to_string()).unwrap_or(String::from(" "));
+    let after: String = rest.collect();
+
+    let texts = [
+        Text::raw(app.command_line.prompt.to_string()),
+        Text::raw(before),
+        Text::styled(cursor, Style::default().modifier(Modifier::REVERSED)),
+        Text::raw(after),
+    ];
+    let mut prompt = Paragraph::new(texts.iter());
+
+    f.render(&mut prompt, area);
+}
+
+// replaces the UI if the terminal is smaller than the minimal size
+fn draw_too_small(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, area : Rect)
+{
+    let message = format!("terminal too small\n{}x{}, needs {}x{}", area.width, area.height, MIN_WIDTH, MIN_HEIGHT);
+    let texts = [Text::raw(message)];
+
+    // vertically centered
+    let top = area.height / 2;
+    let mut paragraph = Paragraph::new(texts.iter()).alignment(Alignment::Center).wrap(true);
+
+    f.render(&mut paragraph, Rect::new(area.x, area.y + top.saturating_sub(1), area.width, area.height - top.saturating_sub(1)));
+}
+
+// draws the keys of the focused block
+fn draw_footer(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+{
+    let footer = help::footer(app);
+    let texts = [Text::styled(footer, Style::default().fg(app.theme.status))];
+    let mut paragraph = Paragraph::new(texts.iter());
+
+    f.render(&mut paragraph, area);
+}
+
+// draws all bindings in a box in the center of the screen
+fn draw_help(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, screen : Rect)
+{
+    let width = (screen.width * 4 / 5).max(screen.width.min(40));
+    let height = (screen.height * 4 / 5).max(screen.height.min(10));
+    let area = Rect::new(screen.x + (screen.width - width) / 2, screen.y + (screen.height - height) / 2, width, height);
+
+    // the lines are padded, as the blocks below would be visible in the empty cells
+    let inner_width = width.saturating_sub(2) as usize;
+    let inner_height = height.saturating_sub(2) as usize;
+    let mut lines = help::help_lines(&app.keymap);
+    while lines.len() < inner_height {
+        lines.push(String::new());
+    }
+    let text: Vec<Text> = lines.iter().map(|line| {
+        let padding = inner_width.saturating_sub(line.chars().count());
+        Text::raw(format!("{}{}\n", line, " ".repeat(padding)))
+    }).collect();
+
+    app.help_scroll = app.help_scroll.min(lines.len().saturating_sub(inner_height) as u16);
+
+    let mut paragraph = Paragraph::new(text.iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
+        .title(" Help (esc to close) ")
+        .title_style(Style::default().fg(app.theme.header)))
+        .scroll(app.help_scroll);
+
+    f.render(&mut paragraph, area);
+}
+
 // draws the sidebar of the UI
-fn draw_sidebar(mut f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+fn draw_sidebar(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
     let chunks = Layout::default() 
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Min(3), // Views
-                Constraint::Length(200), // Playlists
+                Constraint::Length(VIEWS.len() as u16 + 2), // Views
+                Constraint::Min(0), // Playlists or artists
             ]
             .as_ref()
         )
         .split(area);
 
-        draw_view_block(&mut f, app, chunks[0]);
-        draw_playlist_block(&mut f, app, chunks[1]);
+        draw_view_block(f, app, chunks[0]);
+        draw_playlist_block(f, app, chunks[1]);
 
 }
 
@@ -81,8 +220,10 @@ fn draw_sidebar(mut f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut
 fn draw_view_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
+    app.areas.views = area; // used to find the block of mouse clicks
+
     // string that is printed later as a title
-    let rust_cmp_str : &str = "RUST COMMANDLINE MUSIC PLAYER (CMP) ♪ ";
+    let rust_cmp_str : String = format!("RUST COMMANDLINE MUSIC PLAYER (CMP) {} ", app.theme.glyphs.note);
 
     // calculated the content of the on_display object
     // which only contains items currently visible in the UI
@@ -94,14 +235,14 @@ fn draw_view_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut
     }
 
     // get text from all visible list items
-    let items = app.view_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
+    let items = styled_items(&app.view_list, app.current_element == CurrentElement::Views, None, Color::Reset, &app.theme);
 
     // create render object from item list
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
+    let mut render_list = List::new(items.into_iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
         // set the title of the view block
-        .title(rust_cmp_str)
-        .title_style(Style::default().fg(app.header_color)));
+        .title(&rust_cmp_str)
+        .title_style(Style::default().fg(app.theme.header)));
 
     f.render(&mut render_list, area);
 
@@ -116,8 +257,10 @@ fn draw_view_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut
 fn draw_playlist_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
-    // string that is printed later as a title
-    let playlist_str : &str = "Playlists";
+    app.areas.playlists = area; // used to find the block of mouse clicks
+
+    // string that is printed later as a title, the list shows artists while browsing
+    let playlist_str : &str = app.current_view.sidebar_title();
 
     // calculated the content of the on_display object
     // which only contains items currently visible in the UI
@@ -129,13 +272,13 @@ fn draw_playlist_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &
     }
 
     // get text from all visible list items
-    let items = app.playlist_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
+    let items = styled_items(&app.playlist_list, app.current_element == CurrentElement::Playlists, None, Color::Reset, &app.theme);
 
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
+    let mut render_list = List::new(items.into_iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
         // set the title of the view block
         .title(playlist_str)
-        .title_style(Style::default().fg(app.title_color)));
+        .title_style(Style::default().fg(app.theme.title)));
 
     f.render(&mut render_list, area);
 
@@ -147,22 +290,54 @@ fn draw_playlist_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &
 } 
 
 // draw the playbar and the main table in the center of the screen
-fn draw_main_block(mut f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+fn draw_main_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
+    // the playbar is folded into one line if there is not enough space
+    let folded = area.width < PLAYBAR_MIN_WIDTH || area.height < PLAYBAR_MIN_HEIGHT;
+
     let chunks = Layout::default() 
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Min(3), // playbar 
-                Constraint::Length(200), // main area
+                Constraint::Length(if folded { 1 } else { 3 }), // playbar 
+                Constraint::Min(0), // main area
             ]
             .as_ref()
         )
         .split(area);
 
-        draw_play_block(&mut f, app, chunks[0]);
-        draw_selection_block(&mut f, app, chunks[1]);
+        if folded {
+            draw_play_line(f, app, chunks[0]);
+        }
+        else {
+            draw_play_block(f, app, chunks[0]);
+        }
+        if app.current_view.has_table() {
+            draw_selection_block(f, app, chunks[1]);
+        }
+        else {
+            draw_info_block(f, app, chunks[1]);
+        }
+
+}
+
+// draws the text of the views without a table, e.g. the stats
+fn draw_info_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+{
+
+    app.areas.main_area = area; // used to find the block of mouse clicks
+
+    let text = app.info_lines.join("\n");
+    let texts = [Text::raw(text)];
+
+    let mut paragraph = Paragraph::new(texts.iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
+        .title(app.current_view.title())
+        .title_style(Style::default().fg(app.theme.title)))
+        .wrap(true);
+
+    f.render(&mut paragraph, area);
 
 }
 
@@ -175,9 +350,9 @@ fn draw_play_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut
         .constraints(
             [
                 Constraint::Min(18), // tab block
-                Constraint::Percentage(25), // track name
-                Constraint::Percentage(25), // artist name
-                Constraint::Percentage(50), // timeline
+                Constraint::Percentage(app.playbar_widths[0]), // track name
+                Constraint::Percentage(app.playbar_widths[1]), // artist name
+                Constraint::Percentage(app.playbar_widths[2]), // timeline
             ]
             .as_ref()
         )
@@ -190,15 +365,57 @@ fn draw_play_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut
 
 }
 
+// the playbar in a single line without borders: buttons, track and timeline
+fn draw_play_line(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
+{
+
+    let chunks = Layout::default() 
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Length(14), // buttons
+                Constraint::Percentage(50), // track and artist
+                Constraint::Min(10), // timeline
+            ]
+            .as_ref()
+        )
+        .split(area);
+
+    app.areas.tabs = chunks[0];
+    app.areas.timeline = chunks[2];
+
+    let mut tabs = Tabs::default()
+        .titles(&app.playbar_state.titles)
+        .select(app.playbar_state.index)
+        .highlight_style(app.theme.selection_style())
+        .divider(" ");
+    f.render(&mut tabs, chunks[0]);
+
+    let track = format!("{} - {}", app.track_name_list.get_selected_element().trim(), app.artist_name_list.get_selected_element().trim());
+    let texts = [Text::styled(track, Style::default().fg(app.theme.status))];
+    let mut paragraph = Paragraph::new(texts.iter());
+    f.render(&mut paragraph, chunks[1]);
+
+    let label = timeline_label(app);
+    let mut timeline = Gauge::default()
+                .style(Style::default().fg(timeline_color(app)))
+                .ratio(app.current_track_progress)
+                .label(&label);
+    f.render(&mut timeline, chunks[2]);
+
+}
+
 // draw tabs used to select buttons at the top of the screen (play/pause etc.)
 fn draw_tab_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
+    app.areas.tabs = inner(area); // used to find the block of mouse clicks
+
     let mut tabs = Tabs::default()
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border)))
         .titles(&app.playbar_state.titles) // set the content of the tabs items
         .select(app.playbar_state.index) // select an initial item
-        .highlight_style(Style::default().fg(app.title_color)) // color used to highlight selected items
+        .highlight_style(app.theme.selection_style()) // style used to highlight selected items
         .divider(" "); // defines the divider element between the tab elements
 
     f.render(&mut tabs, area);
@@ -221,12 +438,12 @@ fn draw_track_name_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app :
     }
 
     // get text from all visible list items
-    let items = app.track_name_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
+    let items = styled_items(&app.track_name_list, app.current_element == CurrentElement::TrackName, None, app.theme.status, &app.theme);
 
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
+    let mut render_list = List::new(items.into_iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
         .title(track_name_str)
-        .title_style(Style::default().fg(app.title_color)));
+        .title_style(Style::default().fg(app.theme.title)));
 
     f.render(&mut render_list, area);
 
@@ -253,12 +470,12 @@ fn draw_artist_name_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app
     }
 
     // get text from all visible list items
-    let items = app.artist_name_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
+    let items = styled_items(&app.artist_name_list, app.current_element == CurrentElement::ArtistName, None, app.theme.status, &app.theme);
 
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
+    let mut render_list = List::new(items.into_iter())
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
         .title(artist_name_str)
-        .title_style(Style::default().fg(app.title_color)));
+        .title_style(Style::default().fg(app.theme.title)));
 
     f.render(&mut render_list, area);
 
@@ -273,18 +490,16 @@ fn draw_artist_name_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app
 fn draw_timeline_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
-    let mut color = Color::Rgb(0, 95, 210); // default Color for timeline
+    app.areas.timeline = inner(area); // used to find the block of mouse clicks
 
-    // change color of timeline when the it's selected
-    if app.current_element == CurrentElement::Timeline {
-        color = app.title_color;
-    }
+    let color = timeline_color(app);
+    let label = timeline_label(app);
 
     let mut timeline = Gauge::default()
-                .block(Block::default().borders(Borders::ALL))
+                .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border)))
                 .style(Style::default().fg(color))
                 .ratio(app.current_track_progress)
-                .label(&app.track_progress_text);
+                .label(&label);
 
     f.render(&mut timeline, area);
 
@@ -294,161 +509,123 @@ fn draw_timeline_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &
 fn draw_selection_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect)
 {
 
-    let chunks = Layout::default() 
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage(40), // tracks
-                Constraint::Percentage(25), // artists
-                Constraint::Percentage(25), // albums
-                Constraint::Percentage(10), // length
-            ]
-            .as_ref()
-        )
-        .split(area);
-
-    draw_tracks_block(f, app, chunks[0]);
-    draw_artist_block(f, app, chunks[1]);
-    draw_albums_block(f, app, chunks[2]);
-    draw_lengths_block(f, app, chunks[3]);
-
-}
-
-fn draw_tracks_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect) {
-
-    // string that is printed later as a title
-    let tracks_str : &str = "Track";
-
-    // calculated the content of the on_display object
-    // which only contains items currently visible in the UI
-    // this also accounts for horizontal and vertical scrolling
-    app.tracks_list.calc_on_display(area.width as usize, area.height as usize, app.horizontal_scroll_delay);
+    app.areas.main_area = area; // used to find the block of mouse clicks
 
-    if app.current_element == CurrentElement::MainArea {
-        app.tracks_list.add_highlighting_element("> "); // adds the highlighting element to the selected list element
+    // columns are hidden in narrow terminals (see hide_below), the first one is always shown
+    let mut columns: Vec<usize> = (0..app.columns.len()).filter(|c| *c == 0 || area.width >= app.columns[*c].hide_below).collect();
+    let inner_area = inner(area);
+    while columns.len() > 1 && (columns.len() as u16 - 1) * TABLE_COLUMN_SPACING >= inner_area.width {
+        columns.pop();
     }
 
-    // get text from all visible list items
-    let items = app.tracks_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
-
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
-        // set the title of the view block
-        .title(tracks_str)
-        .title_style(Style::default().fg(app.title_color)));
-
-    f.render(&mut render_list, area);
-
-    // removes the highlighting element from the selected list element after rendering
-    if app.current_element == CurrentElement::MainArea {
-        app.tracks_list.remove_highlighting_element('>'); 
-    }
-
-}
-
-fn draw_artist_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect) {
-
-    // string that is printed later as a title
-    let artists_str : &str = "Artist";
-
-    // calculated the content of the on_display object
-    // which only contains items currently visible in the UI
-    // this also accounts for horizontal and vertical scrolling
-    app.artist_list.calc_on_display(area.width as usize, area.height as usize, app.horizontal_scroll_delay);
+    // the space without the gaps between the columns is shared by their widths
+    let spacing = (columns.len() as u16 - 1) * TABLE_COLUMN_SPACING;
+    let total: u32 = columns.iter().map(|c| app.columns[*c].width as u32).sum();
+    let ratios: Vec<Constraint> = columns.iter().map(|c| Constraint::Ratio(app.columns[*c].width as u32, total.max(1))).collect();
+    let widths: Vec<u16> = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(ratios)
+        .split(Rect::new(0, 0, inner_area.width - spacing, 1))
+        .iter().map(|chunk| chunk.width).collect();
+
+    // header with the sort direction
+    let header: Vec<String> = columns.iter().map(|c| {
+        let title = columns::title(&app.columns[*c]);
+        match app.table_order.sort.first() {
+            Some(key) if app.columns[*c].tag.as_ref().map(|t| t.to_lowercase()) == Some(key.tag.clone()) => {
+                format!("{} {}", title, if key.descending { app.theme.glyphs.sort_descending } else { app.theme.glyphs.sort_ascending })
+            },
+            _ => title,
+        }
+    }).collect();
+
+    // the header and the line below it are not part of the rows
+    let visible = app.table.visible_rows(inner_area.height.saturating_sub(2) as usize);
+    app.table.tick(app.horizontal_scroll_delay);
+
+    let active = app.current_element == CurrentElement::MainArea;
+    let rows: Vec<(Vec<String>, Style)> = visible.map(|row| {
+        let selected = active && row == app.table.selected();
+        let cells = columns.iter().zip(widths.iter()).enumerate().map(|(i, (column, width))| {
+            // the selected row starts with the highlighting element
+            if selected && i == 0 {
+                format!("> {}", app.table.cell_text(row, *column, (*width as usize).saturating_sub(2)))
+            }
+            else {
+                app.table.cell_text(row, *column, *width as usize)
+            }
+        }).collect();
 
-    if app.current_element == CurrentElement::MainArea {
-        app.artist_list.add_highlighting_element("> "); // adds the highlighting element to the selected list element
+        let style = if selected {
+            app.theme.selection_style()
+        }
+        else if Some(row) == app.playing_index {
+            app.theme.playing_style()
+        }
+        else {
+            Style::default().fg(Color::Reset)
+        };
+        // marked rows are inverted, a reversed selection of a marked row is shown normal
+        let modifier = if app.table.is_marked(row) { style.modifier ^ Modifier::REVERSED } else { style.modifier };
+        (cells, style.modifier(modifier))
+    }).collect();
+
+    // the title shows the active filter with the number of matching rows and the marked rows
+    let mut title = String::from(app.current_view.title());
+    if !app.table_order.filter.is_empty() {
+        title = format!("{} /{} ({})", title, app.table_order.filter, app.table.len());
     }
-
-    // get text from all visible list items
-    let items = app.artist_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
-
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
-        // set the title of the view block
-        .title(artists_str)
-        .title_style(Style::default().fg(app.title_color)));
-
-    f.render(&mut render_list, area);
-
-    // removes the highlighting element from the selected list element after rendering
-    if app.current_element == CurrentElement::MainArea {
-        app.artist_list.remove_highlighting_element('>'); 
+    if app.table.is_visual() {
+        title = format!("{} [visual: {}]", title, app.table.marked_count());
     }
-
-}
-
-fn draw_albums_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect) {
-
-    // string that is printed later as a title
-    let albums_str : &str = "Album";
-
-    // calculated the content of the on_display object
-    // which only contains items currently visible in the UI
-    // this also accounts for horizontal and vertical scrolling
-    app.albums_list.calc_on_display(area.width as usize, area.height as usize, app.horizontal_scroll_delay);
-
-    if app.current_element == CurrentElement::MainArea {
-        app.albums_list.add_highlighting_element("> "); // adds the highlighting element to the selected list element
+    else if app.table.marked_count() > 0 {
+        title = format!("{} [{} marked]", title, app.table.marked_count());
     }
 
-    // get text from all visible list items
-    let items = app.albums_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
-
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
-        // set the title of the view block
-        .title(albums_str)
-        .title_style(Style::default().fg(app.title_color)));
+    let constraints: Vec<Constraint> = widths.iter().map(|w| Constraint::Length(*w)).collect();
+    let mut table = Table::new(header.iter(), rows.into_iter().map(|(cells, style)| Row::StyledData(cells.into_iter(), style)))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(app.theme.border))
+        .title(&title)
+        .title_style(Style::default().fg(app.theme.title)))
+        .header_style(Style::default().fg(app.theme.header))
+        .widths(&constraints)
+        .column_spacing(TABLE_COLUMN_SPACING);
 
-    f.render(&mut render_list, area);
-
-    // removes the highlighting element from the selected list element after rendering
-    if app.current_element == CurrentElement::MainArea {
-        app.albums_list.remove_highlighting_element('>'); 
-    }
+    f.render(&mut table, area);
 
 }
 
-fn draw_lengths_block(f: &mut Frame<CrosstermBackend<std::io::Stdout>>, app : &mut App, area : Rect) {
-    // string that is printed later as a title
-    let lengths_str : &str = "Length";
-
-    // calculated the content of the on_display object
-    // which only contains items currently visible in the UI
-    // this also accounts for horizontal and vertical scrolling
-    app.lengths_list.calc_on_display(area.width as usize, area.height as usize, app.horizontal_scroll_delay);
-
-    if app.current_element == CurrentElement::MainArea {
-        app.lengths_list.add_highlighting_element("> "); // adds the highlighting element to the selected list element
+// executes the terminal navigation
+pub fn terminal_navigation (app : &mut App) {
+    while let Some(action) = app.poll_navigation() {
+        navigate(app, action);
     }
+}
 
-    // get text from all visible list items
-    let items = app.lengths_list.get_on_display().iter().map(|i| Text::raw(*(&i.as_str())));
-
-    let mut render_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL)
-        // set the title of the view block
-        .title(lengths_str)
-        .title_style(Style::default().fg(app.title_color)));
-
-    f.render(&mut render_list, area);
+// executes a single navigation action
+fn navigate (app : &mut App, action : Action) {
 
-    // removes the highlighting element from the selected list element after rendering
-    if app.current_element == CurrentElement::MainArea {
-        app.lengths_list.remove_highlighting_element('>'); 
+    // actions that do not move between the blocks
+    match action {
+        Action::PageUp | Action::PageDown | Action::First | Action::Last => {
+            jump(app, action);
+            return;
+        },
+        Action::FocusNext | Action::FocusPrevious => {
+            focus(app, action == Action::FocusNext);
+            return;
+        },
+        Action::Mark | Action::MarkUp | Action::MarkDown | Action::VisualMode | Action::ClearMarks => {
+            mark(app, action);
+            return;
+        },
+        _ => {},
     }
 
-}
-
-// executes the terminal navigation
-pub fn terminal_navigation (app : &mut App) {
-    
-    // get the input values
-    let down = app.poll_down();
-    let up = app.poll_up();
-    let left = app.poll_left();
-    let right = app.poll_right();
+    let down = action == Action::Down;
+    let up = action == Action::Up;
+    let left = action == Action::Left;
+    let right = action == Action::Right;
 
     // handle program navigation
 
@@ -558,40 +735,161 @@ pub fn terminal_navigation (app : &mut App) {
     if app.current_element == CurrentElement::MainArea {
 
         if down {
-            app.tracks_list.next();
-            app.artist_list.next();
-            app.albums_list.next();
-            app.lengths_list.next();
+            app.table.next();
         }
 
         if up {
-            if app.tracks_list.is_first_element_selected() {
+            if app.table.is_first_selected() {
                 app.current_element = CurrentElement::Playbar;
                 app.playbar_state.index = 0;
                 return;
             }
 
-            app.tracks_list.previous();
-            app.artist_list.previous();
-            app.albums_list.previous();
-            app.lengths_list.previous();
+            app.table.previous();
         }
 
         if left {
             app.current_element = CurrentElement::Playlists;
             app.playlist_list.reset_selection();
-            return;
         } 
 
     }
 
 }
 
+// moves the selection of the active list by a page or to its start or end
+fn jump (app : &mut App, action : Action) {
+    // selected row, rows of a page and number of rows
+    let (selected, page, length) = match app.current_element {
+        CurrentElement::Views => (app.view_list.get_selected_index(), app.view_list.on_display.len(), app.view_list.len()),
+        CurrentElement::Playlists => (app.playlist_list.get_selected_index(), app.playlist_list.on_display.len(), app.playlist_list.len()),
+        CurrentElement::MainArea => (app.table.selected(), app.table.height(), app.table.len()),
+        _ => return,
+    };
+
+    let page = page.max(1);
+    let index = match action {
+        Action::PageUp => selected.saturating_sub(page),
+        Action::PageDown => selected + page,
+        Action::First => 0,
+        _ => length,
+    };
+
+    match app.current_element {
+        CurrentElement::Views => app.view_list.select(index),
+        CurrentElement::Playlists => app.playlist_list.select(index),
+        _ => app.table.select(index),
+    }
+}
+
+// marks rows of the main area, the marks are kept while other blocks are focused
+fn mark (app : &mut App, action : Action) {
+    if action == Action::ClearMarks {
+        app.table.clear_marks();
+        return;
+    }
+    if app.current_element != CurrentElement::MainArea {
+        return;
+    }
+
+    match action {
+        Action::Mark => {
+            app.table.toggle_mark();
+            let next = app.table.selected() + 1;
+            app.table.select(next); // to mark rows one after another
+        },
+        Action::MarkUp => app.table.extend_mark(false),
+        Action::MarkDown => app.table.extend_mark(true),
+        _ => app.table.toggle_visual(),
+    }
+}
+
+// cycles through views, playlists, main area and playbar
+fn focus (app : &mut App, forward : bool) {
+    let position = match app.current_element {
+        CurrentElement::Views => 0,
+        CurrentElement::Playlists => 1,
+        CurrentElement::MainArea => 2,
+        _ => 3,
+    };
+    let next = if forward { (position + 1) % 4 } else { (position + 3) % 4 };
+
+    app.playbar_state.index = 3;
+    match next {
+        0 => {
+            app.current_element = CurrentElement::Views;
+            app.view_list.reset_selection();
+        },
+        1 => {
+            app.current_element = CurrentElement::Playlists;
+            app.playlist_list.reset_selection();
+        },
+        2 => {
+            app.current_element = CurrentElement::MainArea;
+            reset_main_area_selection(app);
+        },
+        _ => {
+            app.current_element = CurrentElement::Playbar;
+            app.playbar_state.index = 0;
+        },
+    }
+}
+
 fn reset_main_area_selection (app : &mut App) {
 
-    app.tracks_list.reset_selection();
-    app.artist_list.reset_selection();
-    app.albums_list.reset_selection();
-    app.lengths_list.reset_selection();
+    app.table.select(0);
+
+}
+
+// the colour of the timeline, it is highlighted while selected
+fn timeline_color(app : &App) -> Color {
+    if app.current_element == CurrentElement::Timeline {
+        app.theme.gauge_selected
+    }
+    else {
+        app.theme.gauge
+    }
+}
+
+// show the state of the local stream playback and messages next to the progress
+fn timeline_label(app : &App) -> String {
+    let mut label = app.track_progress_text.clone();
+    for status in [&app.listen_status, &app.message].iter() {
+        if !status.is_empty() {
+            label = format!("{} | {}", label, status);
+        }
+    }
 
-}
\ No newline at end of file
+    label
+}
+
+// the area inside the borders of a block
+// Margin can not be constructed with tui 0.8, so the border is removed by hand
+fn inner(area : Rect) -> Rect {
+    if area.width < 2 || area.height < 2 {
+        return Rect::default();
+    }
+    Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2)
+}
+
+// creates the render items of the visible part of a list
+// the selected item is only highlighted while the list is active
+fn styled_items<'l>(list: &'l StatefulSelectedList, active: bool, playing_index: Option<usize>, normal: Color, theme: &Theme) -> Vec<Text<'l>> {
+    let selected = if active { Some(list.get_selected_index_on_display()) } else { None };
+
+    // the playing index refers to all elements, not only the visible ones
+    let playing = playing_index.and_then(|i| i.checked_sub(list.get_offset()));
+
+    list.on_display.iter().enumerate().map(|(i, item)| {
+        let style = if Some(i) == selected {
+            theme.selection_style()
+        }
+        else if Some(i) == playing {
+            theme.playing_style()
+        }
+        else {
+            Style::default().fg(normal)
+        };
+        Text::styled(item.as_str(), style)
+    }).collect()
+}
@@ -5,39 +5,134 @@ use crate::util::stateful_selected_list::{
     CurrentElement
 };
 
-use tui::style::Color;
+use std::collections::VecDeque;
+
+use crate::util::theme::Theme;
+use crate::util::table::Table;
 use argh::FromArgs;
 
+use crate::cli::Command;
+use crate::config::{ColumnConfig, Config, UiConfig};
+use crate::keymap::{Action, Keymap, KeyInput};
+use crate::mouse::{BlockAreas, Click};
+use crate::command_line::CommandLine;
+use crate::sorting::TableOrder;
+use crate::views::{View, VIEWS};
+
 // this struct stores all command line parameters
 #[derive(FromArgs)]
 #[argh(description = "Configure IP address and tick rate of the server ")]
 pub struct CmdArgs {
     
-    #[argh(option, default = "String::from(\"127.0.0.1\")")]
+    // options without a value use the config file or its defaults
+    #[argh(option)]
+    #[argh(description = "path of the config file")]
+    pub config: Option<String>,
+
+    #[argh(option)]
     #[argh(description = "IP Address of the server without port")]
-    pub ip: String, // the ip to connect to
+    pub ip: Option<String>, // the ip to connect to
 
-    #[argh(option, default = "String::from(\"6600\")")]
+    #[argh(option)]
     #[argh(description = "configures port of the server")]
-    pub port: String, // the port to connect to
+    pub port: Option<u16>, // the port to connect to
 
     // time in ms between two ticks.
-    #[argh(option, default = "250")]
+    #[argh(option)]
     #[argh(description = "tick rate of the client")]
-    pub tick_rate: u64,
+    pub tick_rate: Option<u64>,
+
+    // plays files from this directory instead of connecting to a server
+    #[argh(option)]
+    #[argh(description = "play music from a local directory without a MPD server")]
+    pub local: Option<String>,
+
+    // uses a simulated in-memory library instead of a real backend
+    #[argh(switch)]
+    #[argh(description = "run with a made up library without server or audio output")]
+    pub demo: bool,
+
+    // starts a fake mpd server in the background and connects to it
+    #[argh(switch)]
+    #[argh(description = "connect to an in-process fake MPD server serving a made up library")]
+    pub fake_mpd: bool,
+
+    // port of the httpd output used to listen to the server locally
+    #[argh(option)]
+    #[argh(description = "port of the httpd output of the server")]
+    pub stream_port: Option<u16>,
+
+    // overrides the url built from ip and stream port
+    #[argh(option)]
+    #[argh(description = "url of the httpd stream of the server")]
+    pub stream_url: Option<String>,
+
+    // the player is registered on the session bus unless this is set
+    #[argh(switch)]
+    #[argh(description = "do not provide the MPRIS D-Bus interface")]
+    pub no_mpris: bool,
+
+    // desktop notifications are off by default
+    #[argh(switch)]
+    #[argh(description = "show a desktop notification when the song changes")]
+    pub notify: bool,
+
+    // implies notify
+    #[argh(switch)]
+    #[argh(description = "show song notifications only while the terminal is not focused")]
+    pub notify_unfocused: bool,
+
+    // scrobbling is enabled for every service with credentials
+    // the secrets are read from the config file or the environment, see Config::apply_environment
+    #[argh(option)]
+    #[argh(description = "base url of the ListenBrainz compatible server")]
+    pub listenbrainz_url: Option<String>,
+
+    #[argh(option)]
+    #[argh(description = "api key for scrobbling to Last.fm")]
+    pub lastfm_api_key: Option<String>,
+
+    #[argh(option)]
+    #[argh(description = "base url of the Last.fm compatible server")]
+    pub lastfm_url: Option<String>,
+
+    // shell commands that are run on player events
+    #[argh(option)]
+    #[argh(description = "command to run when the song changes")]
+    pub on_song_change: Vec<String>,
+
+    #[argh(option)]
+    #[argh(description = "command to run when playback starts")]
+    pub on_play: Vec<String>,
+
+    #[argh(option)]
+    #[argh(description = "command to run when playback is paused")]
+    pub on_pause: Vec<String>,
+
+    #[argh(option)]
+    #[argh(description = "command to run when playback stops")]
+    pub on_stop: Vec<String>,
+
+    #[argh(option)]
+    #[argh(description = "command to run when the end of the queue is reached")]
+    pub on_queue_end: Vec<String>,
+
+    #[argh(option)]
+    #[argh(description = "command to run when the connection to the server is lost")]
+    pub on_connection_loss: Vec<String>,
+
+    // runs a single command instead of the TUI
+    #[argh(subcommand)]
+    pub command: Option<Command>,
 }
 
-
 pub struct App<'a> {
     pub horizontal_scroll_delay: u16,
 
     // used to hold the contents of the UI lists
     pub view_list: StatefulSelectedList,
     pub playlist_list: StatefulSelectedList,
-    pub tracks_list: StatefulSelectedList,
-    pub artist_list: StatefulSelectedList,
-    pub albums_list: StatefulSelectedList,
-    pub lengths_list: StatefulSelectedList,
+    pub table: Table, // rows of the main area, all columns share the selection
 
     // These values are only for internal use
     pub track_name_list: StatefulSelectedList,
@@ -47,20 +142,45 @@ pub struct App<'a> {
     // has to be betwwen 0 and 1
     pub current_track_progress: f64, 
     pub track_progress_text: String, // string displayed in the progress bar
+    pub listen_status: String, // state of the local stream playback, shown next to the progress
 
     pub current_element: CurrentElement, // currently selected UI block
     pub playbar_state: TabsState<'a>, // currently selected playbar element
     pub should_quit: bool, // if set to true the program exits
 
-    // variables to track user input
-    pub up: bool,
-    pub down: bool,
-    pub left: bool,
-    pub right: bool,
+    // navigation actions of the user, executed by terminal_navigation
+    pub navigation: VecDeque<Action>,
+
+    // set by the reload key, the main loop reloads the config file
+    pub reload_requested: bool,
+    pub message: String, // result of the last command, reload or backend error, shown next to the progress
+
+    // colors and glyphs used in the UI
+    pub theme: Theme,
+
+    // index of the playing song in the main area lists
+    pub playing_index: Option<usize>,
 
-    // colors used in the UI
-    pub header_color: Color,
-    pub title_color: Color,
+    // layout in percent, see UiConfig
+    pub sidebar_width: u16,
+    pub playbar_widths: Vec<u16>,
+    pub columns: Vec<ColumnConfig>,
+
+    pub keymap: Keymap,
+    pub key_input: KeyInput, // keys of an unfinished sequence and the count
+
+    pub command_line: CommandLine, // the ':' prompt, receives all keys while it is active
+    pub help_visible: bool, // the help overlay, receives all keys while it is shown
+    pub help_scroll: u16,
+    pub show_footer: bool, // line with the keys of the focused block
+    pub table_order: TableOrder, // sort and filter of the main table
+    pub default_order: TableOrder, // order of the config, used by views that are shown the first time
+
+    pub current_view: View, // selected in the views block
+    pub info_lines: Vec<String>, // text of the views without a table
+
+    pub areas: BlockAreas, // where the blocks were drawn, used for mouse clicks
+    pub last_click: Option<Click>, // used to detect double clicks
 
 }
 
@@ -71,33 +191,50 @@ impl<'a> App<'a> {
         let mut app = App {
             horizontal_scroll_delay: 1,
 
-            view_list: StatefulSelectedList::new(vec![
-                "CMP is made by K.Radke, L.Seyboldt & S.Stahl (c) 2020  ".to_string(),
-            ]),
+            // alt and the number in front is the default shortcut of the view
+            view_list: StatefulSelectedList::new(VIEWS.iter().enumerate().map(|(i, (_, title))| format!("{} {}", i + 1, title)).collect()),
             playlist_list: StatefulSelectedList::new(vec![" ".to_string()]),
-            tracks_list: StatefulSelectedList::new(vec![" ".to_string()]),
-            artist_list: StatefulSelectedList::new(vec![" ".to_string()]),
-            albums_list: StatefulSelectedList::new(vec![" ".to_string()]),
-            lengths_list: StatefulSelectedList::new(vec![" ".to_string()]),
+            table: Table::default(),
 
             track_name_list: StatefulSelectedList::new(vec![" ".to_string()]),
             artist_name_list: StatefulSelectedList::new(vec![" ".to_string()]),
 
             current_track_progress: 0.0,
             track_progress_text: String::from("00 : 00"),
+            listen_status: String::new(),
             
             current_element: CurrentElement::Playlists,
             
             playbar_state: TabsState::new(vec!["|<<", ">>", ">>|"]), 
             should_quit: false,
 
-            up: false,
-            down: false,
-            left: false,
-            right: false,
+            navigation: VecDeque::new(),
+
+            reload_requested: false,
+            message: String::new(),
+
+            theme: Theme::dark(),
+            playing_index: None,
+
+            sidebar_width: 20,
+            playbar_widths: vec![25, 25, 50],
+            columns: UiConfig::default().columns,
 
-            header_color: Color::Rgb(216, 127, 26),
-            title_color: Color::Rgb(0, 148, 255),
+            keymap: Keymap::preset("default").unwrap(),
+            key_input: KeyInput::default(),
+
+            command_line: CommandLine::new(),
+            help_visible: false,
+            help_scroll: 0,
+            show_footer: true,
+            table_order: TableOrder::default(),
+            default_order: TableOrder::default(),
+
+            current_view: View::Playlists,
+            info_lines: Vec::new(),
+
+            areas: BlockAreas::default(),
+            last_click: None,
         };
 
         app.playbar_state.index = 3; // 3 = empty tab -> nothing is visibly selected
@@ -105,10 +242,6 @@ impl<'a> App<'a> {
         // Select first element
         app.view_list.reset_selection();
         app.playlist_list.reset_selection();
-        app.tracks_list.reset_selection();
-        app.artist_list.reset_selection();
-        app.albums_list.reset_selection();
-        app.lengths_list.reset_selection();
 
         // set artist and track name
         app.set_track_name(String::from(" "));
@@ -117,47 +250,39 @@ impl<'a> App<'a> {
         app.track_name_list.reset_selection();
         app.artist_name_list.reset_selection();
 
-        return app;
-    }
-
-    // +---------------------------------------------------------------+
-    // | polling functions used to read and reset user input variables |
-    // +---------------------------------------------------------------+
-
-    pub fn poll_up (&mut self) -> bool {
-        if self.up {
-            self.up = false;
-            return true;
-        }
-        
-        return false;
+        app
     }
 
-    pub fn poll_down (&mut self) -> bool {
-        if self.down {
-            self.down = false;
-            return true;
+    // applies the ui settings of a validated config
+    pub fn apply_config(&mut self, config: &Config) {
+        self.horizontal_scroll_delay = config.ui.horizontal_scroll_delay;
+        self.sidebar_width = config.ui.sidebar_width;
+        self.playbar_widths = config.ui.playbar_widths.clone();
+        self.columns = config.ui.columns.clone();
+        self.show_footer = config.ui.footer;
+
+        // the filter is kept, it is not part of the config
+        let filter = self.table_order.filter.clone();
+        self.default_order = config.table_order();
+        self.table_order = self.default_order.clone();
+        self.table_order.filter = filter;
+
+        self.theme = config.theme();
+        self.playbar_state.titles = vec![self.theme.glyphs.previous, self.theme.glyphs.play, self.theme.glyphs.next];
+
+        // the config is validated, so this only fails for the default config
+        if let Ok(keymap) = config.keymap() {
+            self.keymap = keymap;
         }
-        
-        return false;
+        self.key_input = KeyInput::default();
     }
 
-    pub fn poll_left (&mut self) -> bool {
-        if self.left {
-            self.left = false;
-            return true;
-        }
-        
-        return false;
-    }
+    // +---------------------------------------------------------------+
+    // | polling function used to read and reset the user navigation   |
+    // +---------------------------------------------------------------+
 
-    pub fn poll_right (&mut self) -> bool {
-        if self.right {
-            self.right = false;
-            return true;
-        }
-        
-        return false;
+    pub fn poll_navigation (&mut self) -> Option<Action> {
+        self.navigation.pop_front()
     }
 
     pub fn set_track_name (&mut self, new_track_name : String) {
@@ -199,5 +324,6 @@ impl<'a> App<'a> {
 // +-----------------------------+
 pub enum Event<I> {
     Input(I),
+    Mouse(crossterm::event::MouseEvent),
     Tick,
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
 pub mod stateful_list;
 pub mod app;
-pub mod stateful_selected_list;
\ No newline at end of file
+pub mod stateful_selected_list;
+pub mod table;
+pub mod paths;
+pub mod theme;
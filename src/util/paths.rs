@@ -0,0 +1,29 @@
+use std::env;
+use std::path::PathBuf;
+
+// +---------------------------------------------------------------+
+// | directories according to the XDG base directory specification |
+// +---------------------------------------------------------------+
+
+// directory for files that can be recreated, e.g. the library index
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+// directory for files written by the user, e.g. the list of radio stations
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+// directory for files written by the program that should be kept, e.g. the command history
+pub fn data_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+// uses the directory in the environment variable or falls back to the given one in $HOME
+fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("rust-cmp")),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback).join("rust-cmp")),
+    }
+}
@@ -2,20 +2,12 @@
 // | from tui-rs repo examples:  |
 // +-----------------------------+
 
+#[derive(Default)]
 pub struct ListState {
     pub offset: usize,
     pub selected: Option<usize>,
 }
 
-impl Default for ListState {
-    fn default() -> ListState {
-        ListState {
-            offset: 0,
-            selected: None,
-        }
-    }
-}
-
 impl ListState {
     pub fn selected(&self) -> Option<usize> {
         self.selected
@@ -45,11 +37,16 @@ impl<T> StatefulList<T> {
     pub fn with_items(items: Vec<T>) -> StatefulList<T> {
         StatefulList {
             state: ListState::default(),
-            items: items,
+            items,
         }
     }
 
     pub fn next(&mut self) {
+        // e.g. the sidebar has no entries without playlists
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -64,6 +61,11 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        // e.g. the sidebar has no entries without playlists
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -89,7 +91,7 @@ pub struct TabsState<'a> {
 }
 
 impl<'a> TabsState<'a> {
-    pub fn new(titles: Vec<&'a str>) -> TabsState {
+    pub fn new(titles: Vec<&'a str>) -> TabsState<'a> {
         TabsState { titles, index: 0 }
     }
 
@@ -61,7 +61,7 @@ impl StatefulSelectedList {
 
         list.change_elements(StatefulList::with_items(content));
 
-        return list;
+        list
     }
 
     pub fn next(&mut self) {
@@ -82,21 +82,36 @@ impl StatefulSelectedList {
             return true;
         }
 
-        return false;
+        false
     }
 
     pub fn is_last_element_selected(&mut self) -> bool {
-        if self.all_elements.state.selected() == Some(self.all_elements.items.len()-1) {
+        if self.all_elements.state.selected() == self.all_elements.items.len().checked_sub(1) {
             return true;
         }
 
-        return false;
+        false
     }
 
     pub fn reset_selection (&mut self) {
         self.all_elements.state.select(Some(0));
     }
 
+    // selects the element at the index, indices after the end select the last element
+    pub fn select(&mut self, index : usize) {
+        let last = self.all_elements.items.len().saturating_sub(1);
+        self.all_elements.state.select(Some(index.min(last)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.all_elements.items.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.all_elements.items.is_empty()
+    }
+
     // function used to change elements of the list
     // this function is nessecary becuase the scroll status 
     // and the on_display varirables have to be reset too
@@ -122,22 +137,37 @@ impl StatefulSelectedList {
 
     }
 
+    #[allow(dead_code)]
     pub fn get_on_display(&mut self) -> &Vec<String> {
         &self.on_display
     }
 
-    // returns the on screen selected element
-    pub fn get_selected_element(&mut self) -> &std::string::String {
-        return &self.on_display[self.selected_element_index_in_on_display];
+    // returns the selected element without scrolling, "" if the list is empty
+    pub fn get_selected_element(&mut self) -> &str {
+        let index = self.get_selected_index();
+        match self.all_elements.items.get(index) {
+            Some(element) => element,
+            None => "",
+        }
     }
 
     pub fn get_selected_index(&mut self) -> usize {
         match self.all_elements.state.selected() {
-            None => return 0,
-            Some(_x) =>  return self.all_elements.state.selected().unwrap()
+            None => 0,
+            Some(_x) =>  self.all_elements.state.selected().unwrap()
         }
     }
 
+    // index of the selected element in on_display
+    pub fn get_selected_index_on_display(&self) -> usize {
+        self.selected_element_index_in_on_display
+    }
+
+    // index of the first element in on_display
+    pub fn get_offset(&self) -> usize {
+        self.all_elements.state.offset
+    }
+
     // removes the text highlighting element from all list elements passed to the function
     pub fn remove_highlighting_element(&mut self, highlighting_element : char) {
 
@@ -151,6 +181,11 @@ impl StatefulSelectedList {
     // adds the highlighting element to the currently selected list item
     pub fn add_highlighting_element(&mut self, highlighting_element : &str) {
 
+        // nothing is visible if the block is too small
+        if self.selected_element_index_in_on_display >= self.on_display.len() {
+            return;
+        }
+
         let selected_element : String  = self.on_display[self.selected_element_index_in_on_display].to_string();
 
         let mut concatenated_element : String = highlighting_element.to_owned();
@@ -162,8 +197,9 @@ impl StatefulSelectedList {
     pub fn calc_on_display (&mut self, mut list_width : usize, mut list_height : usize, horizontal_scroll_delay : u16) {
 
         // list_height and width include the borders of the block. This has to be corrected
-        list_height -= 2; 
-        list_width -= 2;
+        // blocks that are smaller than their borders show nothing
+        list_height = list_height.saturating_sub(2);
+        list_width = list_width.saturating_sub(2);
 
         // clear the existing vector and create a new one with the capacity of the 
         // currently visible part of the list
@@ -221,7 +257,7 @@ impl StatefulSelectedList {
             new_scroll_status = 0;
         }
 
-        return (shifted_string, new_scroll_status); // return the new string and the new scroll status
+        (shifted_string, new_scroll_status)// return the new string and the new scroll status
     }
 
     fn calc_horizontal_scrolling (&mut self, list_width : usize, horizontal_scroll_delay : u16) {
@@ -247,7 +283,7 @@ impl StatefulSelectedList {
 
                 // call the scroll function
                 let scroll_element_return_val = self.scroll_element(&self.on_display[i], // current element in on_display 
-                     self.all_elements_scroll_status[self.all_elements.state.offset+i].scroll_status as u16, // scroll_status of the current element in on_display
+                     self.all_elements_scroll_status[self.all_elements.state.offset+i].scroll_status, // scroll_status of the current element in on_display
                       self.all_elements_scroll_status[self.all_elements.state.offset+i].index, // index in all_elements of the current element in on_display
                        true); // check if the scrolling has resulted in the original string and if so reset the scroll_status
 
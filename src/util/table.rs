@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::player_interface::PlayerInterface;
+
+// a cell of the main table, numbers and durations are right aligned
+#[derive(Clone, PartialEq, Debug)]
+pub enum Cell {
+    Text(String),
+    Number(u64),
+    Duration(Option<i64>), // None for streams without a length
+}
+
+impl Cell {
+    pub fn text(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+            Cell::Duration(Some(seconds)) => PlayerInterface::transform_to_time_string(*seconds),
+            Cell::Duration(None) => String::from("live"),
+        }
+    }
+
+    fn right_aligned(&self) -> bool {
+        !matches!(self, Cell::Text(_))
+    }
+}
+
+// the rows of the main table with one selection and one vertical offset for all columns
+#[derive(Default)]
+pub struct Table {
+    rows: Vec<Vec<Cell>>,
+    selected: usize,
+    offset: usize, // index of the first visible row
+    height: usize, // number of visible rows, set while drawing
+    marquee: usize, // characters the long cells of the selected row are scrolled
+    tick_counter: u16, // draws since the last marquee step
+    marked: BTreeSet<usize>, // rows marked for batch operations
+    visual_start: Option<usize>, // row where the visual mode started, the range to the selection is marked
+}
+
+impl Table {
+    // replaces all rows and selects the first one, the marks are removed
+    pub fn set_rows(&mut self, rows: Vec<Vec<Cell>>) {
+        self.rows = rows;
+        self.offset = 0;
+        self.select(0);
+        self.clear_marks();
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // selects the row at the index, indices after the end select the last row
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.rows.len().saturating_sub(1));
+        self.marquee = 0;
+        self.tick_counter = 0;
+    }
+
+    // the selection wraps around at both ends, like the other lists
+    pub fn next(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.select(self.selected + 1);
+        }
+        else {
+            self.select(0);
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if self.selected > 0 {
+            self.select(self.selected - 1);
+        }
+        else {
+            self.select(self.rows.len());
+        }
+    }
+
+    // marked rows are used by the batch operations instead of the selected row
+    pub fn toggle_mark(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        if !self.marked.remove(&self.selected) {
+            self.marked.insert(self.selected);
+        }
+    }
+
+    // marks the selected row, moves the selection and marks the new row (shift and an arrow key)
+    pub fn extend_mark(&mut self, down: bool) {
+        if self.rows.is_empty() {
+            return;
+        }
+        self.marked.insert(self.selected);
+        let next = if down { self.selected + 1 } else { self.selected.saturating_sub(1) };
+        self.select(next);
+        self.marked.insert(self.selected);
+    }
+
+    // starts the visual mode, ending it keeps the range marked
+    pub fn toggle_visual(&mut self) {
+        match self.visual_start.take() {
+            Some(start) => self.marked.extend(start.min(self.selected)..=start.max(self.selected)),
+            None if !self.rows.is_empty() => self.visual_start = Some(self.selected),
+            None => {},
+        }
+    }
+
+    pub fn is_visual(&self) -> bool {
+        self.visual_start.is_some()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.visual_start = None;
+    }
+
+    // marks these rows instead of the current ones, e.g. after the rows were moved
+    pub fn set_marks(&mut self, rows: &[usize]) {
+        self.clear_marks();
+        let length = self.rows.len();
+        self.marked.extend(rows.iter().filter(|row| **row < length));
+    }
+
+    pub fn is_marked(&self, row: usize) -> bool {
+        match self.visual_start {
+            Some(start) if row >= start.min(self.selected) && row <= start.max(self.selected) => true,
+            _ => self.marked.contains(&row),
+        }
+    }
+
+    // the rows the batch operations work on: the marked rows, otherwise the selected one
+    pub fn target_rows(&self) -> Vec<usize> {
+        let marked: Vec<usize> = (0..self.rows.len()).filter(|row| self.is_marked(*row)).collect();
+        if !marked.is_empty() || self.rows.is_empty() {
+            return marked;
+        }
+        vec![self.selected]
+    }
+
+    pub fn marked_count(&self) -> usize {
+        (0..self.rows.len()).filter(|row| self.is_marked(*row)).count()
+    }
+
+    pub fn is_first_selected(&self) -> bool {
+        self.selected == 0
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // number of rows that were visible at the last draw, used to page
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // the rows that fit into the height, the offset follows the selection
+    pub fn visible_rows(&mut self, height: usize) -> Range<usize> {
+        self.height = height;
+        if self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+        else if self.selected < self.offset {
+            self.offset = self.selected;
+        }
+        // rows below the end are filled if the table got higher
+        self.offset = self.offset.min(self.rows.len().saturating_sub(height));
+
+        self.offset..(self.offset + height).min(self.rows.len())
+    }
+
+    // advances the marquee of the selected row every delay draws
+    pub fn tick(&mut self, horizontal_scroll_delay: u16) {
+        self.tick_counter += 1;
+        if self.tick_counter >= horizontal_scroll_delay {
+            self.tick_counter = 0;
+            self.marquee = self.marquee.wrapping_add(1);
+        }
+    }
+
+    // the text of a cell fitted to the width of its column
+    // long cells of the selected row scroll, the others are cut
+    pub fn cell_text(&self, row: usize, column: usize, width: usize) -> String {
+        let cell = match self.rows.get(row).and_then(|cells| cells.get(column)) {
+            Some(c) => c,
+            None => return String::new(),
+        };
+        let text = cell.text();
+        let length = text.chars().count();
+
+        if length > width {
+            if row != self.selected || width < 3 {
+                return text.chars().take(width).collect();
+            }
+            // the text is rotated with a space between its end and its start
+            let shift = self.marquee % (length + 1);
+            return text.chars().chain(" ".chars()).chain(text.chars()).skip(shift).take(width).collect();
+        }
+
+        if cell.right_aligned() {
+            format!("{:>width$}", text, width = width)
+        }
+        else {
+            text
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::env;
+
+use tui::style::{Color, Modifier, Style};
+
+// names of the built-in themes
+pub const THEME_NAMES: [&str; 2] = ["dark", "light"];
+
+// colours of all parts of the UI
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub border: Color,
+    pub header: Color, // title of the views block
+    pub title: Color, // titles of all other blocks
+    pub selection: Color, // selected list item and playbar button
+    pub playing: Color, // row of the song that is currently playing
+    pub gauge: Color, // timeline
+    pub gauge_selected: Color, // timeline while it is selected
+    pub status: Color, // current track, artist and status messages
+    pub glyphs: Glyphs,
+}
+
+// symbols used in the UI, not every terminal font has the unicode ones
+#[derive(Clone, Debug)]
+pub struct Glyphs {
+    pub note: &'static str,
+    pub play: &'static str,
+    pub pause: &'static str,
+    pub previous: &'static str,
+    pub next: &'static str,
+    pub sort_ascending: &'static str, // behind the title of the sorted column
+    pub sort_descending: &'static str,
+}
+
+pub const UNICODE_GLYPHS: Glyphs = Glyphs {
+    note: "♪",
+    play: ">>",
+    pause: "‖‖",
+    previous: "|<<",
+    next: ">>|",
+    sort_ascending: "▲",
+    sort_descending: "▼",
+};
+
+pub const ASCII_GLYPHS: Glyphs = Glyphs {
+    note: "~",
+    play: ">>",
+    pause: "||",
+    previous: "|<<",
+    next: ">>|",
+    sort_ascending: "^",
+    sort_descending: "v",
+};
+
+// number of colours the terminal can show
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    NoColor,
+}
+
+impl Theme {
+    // the original colours of rust-cmp
+    pub fn dark() -> Theme {
+        Theme {
+            border: Color::Reset,
+            header: Color::Rgb(216, 127, 26),
+            title: Color::Rgb(0, 148, 255),
+            selection: Color::Rgb(0, 148, 255),
+            playing: Color::Rgb(216, 127, 26),
+            gauge: Color::Rgb(0, 95, 210),
+            gauge_selected: Color::Rgb(0, 148, 255),
+            status: Color::Reset,
+            glyphs: UNICODE_GLYPHS,
+        }
+    }
+
+    // darker colours that are readable on a white background
+    pub fn light() -> Theme {
+        Theme {
+            border: Color::Rgb(120, 120, 120),
+            header: Color::Rgb(166, 77, 0),
+            title: Color::Rgb(0, 80, 170),
+            selection: Color::Rgb(0, 80, 170),
+            playing: Color::Rgb(166, 77, 0),
+            gauge: Color::Rgb(0, 110, 190),
+            gauge_selected: Color::Rgb(0, 80, 170),
+            status: Color::Rgb(40, 40, 40),
+            glyphs: UNICODE_GLYPHS,
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            _ => None,
+        }
+    }
+
+    // converts all colours to ones the terminal can show
+    pub fn downgrade(mut self, depth: ColorDepth) -> Theme {
+        for color in self.colors_mut() {
+            *color = downgrade_color(*color, depth);
+        }
+
+        self
+    }
+
+    // without colours (NO_COLOR, or "reset" in the config) the selection is reversed
+    pub fn selection_style(&self) -> Style {
+        let style = Style::default().fg(self.selection);
+        if self.selection == Color::Reset { style.modifier(Modifier::REVERSED) } else { style }
+    }
+
+    // without colours the playing row is bold
+    pub fn playing_style(&self) -> Style {
+        let style = Style::default().fg(self.playing);
+        if self.playing == Color::Reset { style.modifier(Modifier::BOLD) } else { style }
+    }
+
+    fn colors_mut(&mut self) -> Vec<&mut Color> {
+        vec![
+            &mut self.border,
+            &mut self.header,
+            &mut self.title,
+            &mut self.selection,
+            &mut self.playing,
+            &mut self.gauge,
+            &mut self.gauge_selected,
+            &mut self.status,
+        ]
+    }
+}
+
+// guesses the colour support from the environment
+// NO_COLOR disables colours, see https://no-color.org
+pub fn detect_color_depth() -> ColorDepth {
+    if env::var_os("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false) {
+        return ColorDepth::NoColor;
+    }
+
+    let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorDepth::TrueColor;
+    }
+
+    let term = env::var("TERM").unwrap_or_default().to_lowercase();
+    if term == "dumb" {
+        ColorDepth::NoColor
+    }
+    else if term.contains("256color") {
+        ColorDepth::Ansi256
+    }
+    else {
+        ColorDepth::Ansi16
+    }
+}
+
+// returns true if the locale uses utf-8, otherwise the ascii glyphs are used
+pub fn detect_unicode() -> bool {
+    // the first variable that is set decides, like in setlocale
+    for variable in ["LC_ALL", "LC_CTYPE", "LANG"].iter() {
+        if let Ok(value) = env::var(variable) {
+            if !value.is_empty() {
+                let value = value.to_lowercase();
+                return value.contains("utf-8") || value.contains("utf8");
+            }
+        }
+    }
+
+    false
+}
+
+fn downgrade_color(color: Color, depth: ColorDepth) -> Color {
+    match (color, depth) {
+        (_, ColorDepth::NoColor) => Color::Reset,
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Indexed(rgb_to_256(r, g, b)),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => rgb_to_16(r, g, b),
+        (Color::Indexed(i), ColorDepth::Ansi16) if i >= 16 => {
+            let (r, g, b) = indexed_to_rgb(i);
+            rgb_to_16(r, g, b)
+        },
+        (c, _) => c,
+    }
+}
+
+// +--------------------------------------------------------------------+
+// | the 256 colour palette: 16 system colours, a 6x6x6 cube, 24 greys |
+// +--------------------------------------------------------------------+
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| CUBE_LEVELS.iter().enumerate().min_by_key(|(_, l)| (**l as i32 - v as i32).abs()).map(|(i, _)| i as u8).unwrap_or(0);
+    let (cr, cg, cb) = (level(r), level(g), level(b));
+    let cube_index = 16 + 36 * cr + 6 * cg + cb;
+
+    // greys are closer to one of the 24 grey levels than to the cube
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = if average < 8 { 0 } else { ((average - 8) / 10).min(23) as u8 };
+    let grey = 8 + grey_index as u32 * 10;
+
+    let distance = |(x, y, z): (u8, u8, u8)| {
+        (x as i32 - r as i32).pow(2) + (y as i32 - g as i32).pow(2) + (z as i32 - b as i32).pow(2)
+    };
+    let cube_distance = distance((CUBE_LEVELS[cr as usize], CUBE_LEVELS[cg as usize], CUBE_LEVELS[cb as usize]));
+    let grey_distance = distance((grey as u8, grey as u8, grey as u8));
+
+    if grey_distance < cube_distance { 232 + grey_index } else { cube_index }
+}
+
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    if index >= 232 {
+        let grey = 8 + (index - 232) * 10;
+        (grey, grey, grey)
+    }
+    else {
+        let i = index - 16;
+        (CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[(i / 6 % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+    }
+}
+
+// picks the closest of the 16 system colours, their exact values depend on the terminal
+fn rgb_to_16(r: u8, g: u8, b: u8) -> Color {
+    let palette = [
+        (Color::Black, (0, 0, 0)),
+        (Color::Red, (205, 0, 0)),
+        (Color::Green, (0, 205, 0)),
+        (Color::Yellow, (205, 205, 0)),
+        (Color::Blue, (0, 0, 238)),
+        (Color::Magenta, (205, 0, 205)),
+        (Color::Cyan, (0, 205, 205)),
+        (Color::Gray, (229, 229, 229)),
+        (Color::DarkGray, (127, 127, 127)),
+        (Color::LightRed, (255, 0, 0)),
+        (Color::LightGreen, (0, 255, 0)),
+        (Color::LightYellow, (255, 255, 0)),
+        (Color::LightBlue, (92, 92, 255)),
+        (Color::LightMagenta, (255, 0, 255)),
+        (Color::LightCyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+
+    palette.iter()
+        .min_by_key(|(_, (pr, pg, pb))| {
+            (*pr - r as i32).pow(2) + (*pg - g as i32).pow(2) + (*pb - b as i32).pow(2)
+        })
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
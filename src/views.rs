@@ -0,0 +1,63 @@
+use crate::backend::Track;
+use crate::sorting::TableOrder;
+use crate::util::table::Table;
+
+// the views of the sidebar
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum View {
+    Playlists, // tracks of the playlist selected in the sidebar
+    Queue,
+    Library, // all tracks of the music database
+    Browse, // tracks of the artist selected in the sidebar
+    Search, // tracks of the library that match the '/' filter
+    NowPlaying, // tags of the current track
+    Stats,
+}
+
+// all views in the order of the sidebar, the number keys select them
+pub const VIEWS: [(View, &str); 7] = [
+    (View::Playlists, "Playlists"),
+    (View::Queue, "Queue"),
+    (View::Library, "Library"),
+    (View::Browse, "Browse"),
+    (View::Search, "Search"),
+    (View::NowPlaying, "Now Playing"),
+    (View::Stats, "Stats"),
+];
+
+impl View {
+    pub fn from_index(index: usize) -> View {
+        VIEWS.get(index).map(|(view, _)| *view).unwrap_or(View::Playlists)
+    }
+
+    pub fn index(&self) -> usize {
+        VIEWS.iter().position(|(view, _)| view == self).unwrap_or(0)
+    }
+
+    pub fn title(&self) -> &'static str {
+        VIEWS[self.index()].1
+    }
+
+    // now playing and stats show text instead of the table
+    pub fn has_table(&self) -> bool {
+        !matches!(self, View::NowPlaying | View::Stats)
+    }
+
+    // the list below the views lists artists while browsing
+    pub fn sidebar_title(&self) -> &'static str {
+        match self {
+            View::Browse => "Artists",
+            _ => "Playlists",
+        }
+    }
+}
+
+// the content of a hidden view, it is restored when the view is shown again
+pub struct ViewState {
+    pub source: String, // name of the shown playlist or artist
+    pub tracks: Vec<Track>,
+    pub rows: Vec<usize>, // see PlayerInterface::rows
+    pub table: Table,
+    pub order: TableOrder,
+    pub sidebar_index: usize, // selected playlist or artist
+}